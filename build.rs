@@ -0,0 +1,28 @@
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn main() {
+    // Git metadata is best effort, builds without git or a checkout (e.g. in
+    // Docker) can pass `GIT_SHA` explicitly and otherwise report "unknown"
+    let git_sha = std::env::var("GIT_SHA").ok().or_else(|| {
+        Command::new("git")
+            .args(["rev-parse", "HEAD"])
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .and_then(|output| String::from_utf8(output.stdout).ok())
+    });
+    if let Some(git_sha) = git_sha {
+        println!("cargo:rustc-env=GIT_SHA={}", git_sha.trim());
+    }
+
+    let build_timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+    println!("cargo:rustc-env=BUILD_TIMESTAMP={build_timestamp}");
+
+    println!("cargo:rerun-if-env-changed=GIT_SHA");
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs");
+}
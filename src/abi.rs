@@ -6,7 +6,13 @@ sol! {
     interface IWorldIDIdentityManager {
         #[derive(Debug, Serialize, Deserialize)]
         event TreeChanged(uint256 indexed preRoot, uint8 indexed kind, uint256 indexed postRoot);
+        struct RootInfo {
+            uint256 root;
+            uint128 supersededTimestamp;
+            bool isValid;
+        }
         function latestRoot() external returns (uint256);
+        function queryRoot(uint256 root) external view returns (RootInfo memory);
         function registerIdentities(uint256[8] calldata insertionProof, uint256 preRoot, uint32 startIndex, uint256[] calldata identityCommitments, uint256 postRoot) external;
         function deleteIdentities(uint256[8] calldata deletionProof, bytes calldata packedDeletionIndices, uint256 preRoot, uint256 postRoot) external;
     }
@@ -1,23 +1,88 @@
+use std::collections::BTreeMap;
 use std::fmt::Debug;
 use std::future::Future;
 use std::marker::PhantomData;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 
-use alloy::eips::BlockNumberOrTag;
+use alloy::eips::{BlockId, BlockNumberOrTag};
 use alloy::network::Ethereum;
-use alloy::providers::Provider;
-use alloy::rpc::types::{Filter, Log};
+use alloy::primitives::B256;
+use alloy::providers::{Provider, ProviderBuilder, RootProvider, WsConnect};
+use alloy::pubsub::{PubSubFrontend, Subscription};
+use alloy::rpc::types::{BlockTransactionsKind, Filter, Log};
 use alloy::sol_types::SolEvent;
-use alloy::transports::Transport;
+use alloy::transports::{Transport, TransportError, TransportResult};
 use eyre::Result;
-use futures::{stream, FutureExt as _, Stream, StreamExt as _};
+use futures::{future, stream, FutureExt as _, Stream, StreamExt as _};
+use telemetry_batteries::reexports::metrics;
+use url::Url;
 
 use crate::abi::IWorldIDIdentityManager::TreeChanged;
+use crate::checkpoint::Checkpoint;
+use crate::config::Finality;
 use crate::utils::retry;
 
 pub const BLOCK_SCANNER_SLEEP_TIME: u64 = 5;
 
+pub const DEFAULT_SCAN_CONCURRENCY: usize = 10;
+
+/// Sizes the block range of each log query.
+///
+/// When adaptive, the window doubles (up to `max_size`) after windows that
+/// returned few logs and halves after windows approaching `log_threshold`,
+/// keeping sync fast on quiet chains without tripping provider limits on busy
+/// ones.
+#[derive(Debug)]
+pub struct WindowSizer {
+    size: AtomicU64,
+    adaptive: Option<AdaptiveWindow>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct AdaptiveWindow {
+    pub max_size: u64,
+    pub log_threshold: u64,
+}
+
+impl WindowSizer {
+    pub fn new(size: u64, adaptive: Option<AdaptiveWindow>) -> Self {
+        metrics::gauge!("scanner_window_size").set(size as f64);
+        Self {
+            size: AtomicU64::new(size),
+            adaptive,
+        }
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size.load(Ordering::Relaxed)
+    }
+
+    /// Adjusts the window to the number of logs the last window returned.
+    fn record(&self, logs: usize) {
+        let Some(adaptive) = self.adaptive else {
+            return;
+        };
+
+        let logs = logs as u64;
+        let size = self.size();
+        let new_size = if logs * 5 >= adaptive.log_threshold * 4 {
+            (size / 2).max(1)
+        } else if logs * 4 < adaptive.log_threshold {
+            (size * 2).min(adaptive.max_size)
+        } else {
+            size
+        };
+
+        if new_size != size {
+            self.size.store(new_size, Ordering::Relaxed);
+            metrics::gauge!("scanner_window_size").set(new_size as f64);
+            tracing::debug!(logs, size, new_size, "Adjusted scan window size");
+        }
+    }
+}
+
 /// The `BlockScanner` utility tool enables allows parsing arbitrary onchain events
 #[derive(Debug)]
 pub struct BlockScanner<T, P, N = Ethereum>
@@ -29,10 +94,27 @@ where
     pub provider: Arc<P>,
     /// The block from which to start parsing a given event
     pub start_block: u64,
-    /// The maximum block range to parse
-    window_size: u64,
+    /// The last block to parse, the scanner runs forever if unset
+    pub end_block: Option<u64>,
+    /// Sizes the block range parsed at once
+    window: Arc<WindowSizer>,
     /// Filter specifying the address and topics to match on when scanning
     filter: Filter,
+    /// The number of confirmations after which a block is considered safe from reorgs
+    reorg_depth: u64,
+    /// Websocket endpoint used to subscribe to new logs instead of polling
+    ws_endpoint: Option<Url>,
+    /// The block windows are scanned up to
+    finality: BlockNumberOrTag,
+    /// Records the last fully processed block
+    checkpoint: Option<Checkpoint>,
+    /// The block after the last fully processed one, restarted streams
+    /// resume from it
+    resume_block: AtomicU64,
+    /// The maximum number of log queries in flight at once
+    scan_concurrency: usize,
+    /// Caps how fast windows advance while catching up to the chain head
+    max_blocks_per_second: Option<u64>,
     chain_id: u64,
     _marker: PhantomData<(T, N)>,
 }
@@ -45,42 +127,140 @@ where
     /// Initializes a new `BlockScanner`
     pub async fn new(
         provider: Arc<P>,
-        window_size: u64,
+        window: WindowSizer,
         start_block: u64,
         filter: Filter,
+        reorg_depth: u64,
+        ws_endpoint: Option<Url>,
+        checkpoint: Option<Checkpoint>,
     ) -> Result<Self> {
         let chain_id = provider.get_chain_id().await?;
         Ok(Self {
             provider,
             start_block,
-            window_size,
+            end_block: None,
+            window: Arc::new(window),
             filter,
+            reorg_depth,
+            ws_endpoint,
+            finality: BlockNumberOrTag::Latest,
+            checkpoint,
+            resume_block: AtomicU64::new(start_block),
+            scan_concurrency: DEFAULT_SCAN_CONCURRENCY,
+            max_blocks_per_second: None,
             chain_id,
             _marker: PhantomData,
         })
     }
 
+    /// Sets the maximum number of log queries in flight at once.
+    pub fn with_scan_concurrency(mut self, scan_concurrency: usize) -> Self {
+        self.scan_concurrency = scan_concurrency.max(1);
+        self
+    }
+
+    /// Caps how many blocks per second are scanned while catching up, keeping
+    /// bursts of log queries within the provider's budget.
+    pub fn with_max_blocks_per_second(
+        mut self,
+        max_blocks_per_second: Option<u64>,
+    ) -> Self {
+        self.max_blocks_per_second =
+            max_blocks_per_second.map(|blocks| blocks.max(1));
+        self
+    }
+
+    /// Only scans blocks that have reached the commitment.
+    pub fn with_finality(mut self, finality: Finality) -> Self {
+        self.finality = finality.into();
+        self
+    }
+
+    /// Stops scanning once `end_block` has been scanned, for bounded
+    /// backfills.
+    pub fn with_end_block(mut self, end_block: Option<u64>) -> Self {
+        self.end_block = end_block;
+        self
+    }
+
+    /// Whether every block up to the end block has been processed, always
+    /// false for unbounded scanners.
+    pub fn finished(&self) -> bool {
+        self.end_block
+            .is_some_and(|end_block| self.resume_block() > end_block)
+    }
+
+    /// The block streams start from, after the last fully processed block.
+    fn resume_block(&self) -> u64 {
+        self.resume_block.load(Ordering::Relaxed)
+    }
+
+    /// Records that every block up to `block` has been processed.
+    fn mark_synced(&self, block: u64) {
+        self.resume_block.fetch_max(block + 1, Ordering::Relaxed);
+        if let Some(checkpoint) = &self.checkpoint {
+            checkpoint.write(block);
+        }
+    }
+
     pub fn block_stream(
         &self,
-    ) -> impl Stream<Item: Future<Output = Result<Vec<Log>>> + Send> + '_ {
+    ) -> impl Stream<Item: Future<Output = Result<(Vec<Log>, u64)>> + Send> + '_
+    {
+        self.block_stream_from(self.start_block, self.end_block)
+    }
+
+    /// Polls for logs in windows starting at `start_block`, yielding each
+    /// window's logs along with its last block.
+    ///
+    /// The stream completes once `end_block` has been scanned, if set.
+    fn block_stream_from(
+        &self,
+        start_block: u64,
+        end_block: Option<u64>,
+    ) -> impl Stream<Item: Future<Output = Result<(Vec<Log>, u64)>> + Send> + '_
+    {
         stream::unfold(
-            (self.start_block, 0),
-            move |(next_block, mut latest)| async move {
+            (start_block, 0, BTreeMap::new(), tokio::time::Instant::now()),
+            move |(
+                mut next_block,
+                mut latest,
+                mut unsafe_blocks,
+                mut paced_until,
+            )| async move {
+                if end_block.is_some_and(|end_block| next_block > end_block) {
+                    return None;
+                }
+
+                if let Some(fork_block) =
+                    self.detect_reorg(&mut unsafe_blocks).await
+                {
+                    next_block = next_block.min(fork_block);
+                }
+
                 let to_block = loop {
-                    let try_to = next_block + self.window_size;
+                    let try_to = next_block + self.window.size();
                     // Update the latest block number only if required
                     if try_to > latest {
                         let provider = self.provider.clone();
-                        latest = retry(
+                        let finality = self.finality;
+                        latest = match retry(
                             Duration::from_millis(100),
                             Some(Duration::from_secs(60)),
-                            move || {
-                                let provider = provider.clone();
-                                async move { provider.get_block_number().await }
-                            },
+                            move || head_block(provider.clone(), finality),
                         )
                         .await
-                        .expect("failed to fetch latest block after retry");
+                        {
+                            Ok(latest) => latest,
+                            Err(error) => {
+                                tracing::error!(
+                                    %error,
+                                    chain_id = self.chain_id,
+                                    "Failed to fetch the latest block, ending the block stream"
+                                );
+                                return None;
+                            }
+                        };
                         if latest < next_block {
                             tokio::time::sleep(Duration::from_secs(
                                 BLOCK_SCANNER_SLEEP_TIME,
@@ -94,15 +274,37 @@ where
 
                     break (try_to).min(latest);
                 };
-                let filter = Arc::new(
-                    self.filter
-                        .clone()
-                        .from_block(BlockNumberOrTag::from(next_block))
-                        .to_block(BlockNumberOrTag::from(to_block)),
-                );
+                let to_block = end_block
+                    .map_or(to_block, |end_block| to_block.min(end_block));
+
+                // Windows at the head are already paced by new blocks
+                if let Some(max_blocks_per_second) = self.max_blocks_per_second
+                {
+                    if to_block < latest {
+                        tokio::time::sleep_until(paced_until).await;
+                        let blocks = to_block - next_block + 1;
+                        paced_until = paced_until
+                            .max(tokio::time::Instant::now())
+                            + Duration::from_secs_f64(
+                                blocks as f64 / max_blocks_per_second as f64,
+                            );
+                    }
+                }
+                let filter = Arc::new(self.filter.clone());
                 let last_synced_block = next_block;
 
+                if self.reorg_depth > 0 {
+                    self.record_unsafe_blocks(
+                        &mut unsafe_blocks,
+                        next_block,
+                        to_block,
+                        latest,
+                    )
+                    .await;
+                }
+
                 let provider = self.provider.clone();
+                let window = self.window.clone();
                 let chain_id = self.chain_id;
 
                 // This future is yielded from the stream
@@ -113,31 +315,358 @@ where
                     move || {
                         let provider = provider.clone();
                         let filter = filter.clone();
+                        let window = window.clone();
                         async move {
                             tracing::trace!(?chain_id, ?last_synced_block,);
-                            let logs = provider.get_logs(&filter).await?;
-                            Ok(logs)
+                            let logs = get_logs_split(
+                                provider,
+                                filter,
+                                last_synced_block,
+                                to_block,
+                            )
+                            .await?;
+                            let chain_id = chain_id.to_string();
+                            metrics::gauge!("last_synced_block", "chain_id" => chain_id.clone())
+                                .set(to_block as f64);
+                            metrics::gauge!("scanner_lag_blocks", "chain_id" => chain_id)
+                                .set(latest.saturating_sub(to_block) as f64);
+                            window.record(logs.len());
+                            Ok((logs, to_block))
                         }
                     },
                 );
 
-                Some((fut, (to_block + 1, latest)))
+                Some((fut, (to_block + 1, latest, unsafe_blocks, paced_until)))
             },
         )
     }
 
+    /// Records the hashes of scanned blocks that are not yet buried under
+    /// `reorg_depth` confirmations, forgetting those that now are.
+    async fn record_unsafe_blocks(
+        &self,
+        unsafe_blocks: &mut BTreeMap<u64, B256>,
+        from_block: u64,
+        to_block: u64,
+        latest: u64,
+    ) {
+        let safe_block = latest.saturating_sub(self.reorg_depth);
+        unsafe_blocks.retain(|number, _| *number > safe_block);
+
+        for number in from_block.max(safe_block + 1)..=to_block {
+            if let Some(hash) = self.block_hash(number).await {
+                unsafe_blocks.insert(number, hash);
+            }
+        }
+    }
+
+    /// Compares the recorded unsafe block hashes against the chain, returning
+    /// the first block that must be rescanned if a reorg occurred.
+    async fn detect_reorg(
+        &self,
+        unsafe_blocks: &mut BTreeMap<u64, B256>,
+    ) -> Option<u64> {
+        let (&tip, &tip_hash) = unsafe_blocks.last_key_value()?;
+        if self.block_hash(tip).await? == tip_hash {
+            return None;
+        }
+
+        // Walk back until the recorded hash matches the chain again
+        let mut fork_block = tip;
+        let mut reorged = (tip, tip_hash, None);
+        for (&number, &recorded) in unsafe_blocks.iter().rev() {
+            let current = self.block_hash(number).await;
+            if current == Some(recorded) {
+                break;
+            }
+            fork_block = number;
+            reorged = (number, recorded, current);
+        }
+
+        let (number, recorded, current) = reorged;
+        tracing::warn!(
+            chain_id = self.chain_id,
+            block = number,
+            recorded_hash = %recorded,
+            current_hash = ?current,
+            fork_block,
+            "Reorg detected, rescanning from the fork point"
+        );
+
+        unsafe_blocks.retain(|number, _| *number < fork_block);
+        Some(fork_block)
+    }
+
+    async fn block_hash(&self, number: u64) -> Option<B256> {
+        let provider = self.provider.clone();
+        let block = retry(
+            Duration::from_millis(100),
+            Some(Duration::from_secs(60)),
+            move || {
+                let provider = provider.clone();
+                async move {
+                    provider
+                        .get_block(
+                            BlockId::number(number),
+                            BlockTransactionsKind::Hashes,
+                        )
+                        .await
+                }
+            },
+        )
+        .await
+        .ok()??;
+
+        Some(block.header.hash)
+    }
+
     /// Creates a stream of `TreeChanged` events
-    pub fn root_stream(&self) -> impl Stream<Item = TreeChanged> + '_ {
-        self.block_stream().buffered(10).flat_map(|logs| {
-            let fut = async move {
-                let logs: Vec<Log> = logs.unwrap();
-                stream::iter(logs.into_iter().filter_map(|log| {
-                    TreeChanged::decode_log(&log.inner, false)
-                        .ok()
-                        .map(|l| l.data)
-                }))
+    ///
+    /// If a websocket endpoint is configured new events are streamed from a log
+    /// subscription, otherwise the chain is polled in windows. The stream ends
+    /// if a window can't be fetched, a new stream resumes after the last fully
+    /// processed block.
+    ///
+    /// Bounded scanners always poll, and their stream completes once the end
+    /// block has been scanned. Scanners waiting for safe or finalized blocks
+    /// poll as well, subscriptions deliver logs at the chain head.
+    pub fn root_stream(&self) -> impl Stream<Item = TreeChangedLog> + '_ {
+        match (self.ws_endpoint.clone(), self.end_block) {
+            (Some(ws_endpoint), None)
+                if self.finality == BlockNumberOrTag::Latest =>
+            {
+                self.subscription_root_stream(ws_endpoint).left_stream()
+            }
+            (_, end_block) => self
+                .polling_root_stream(self.resume_block(), end_block)
+                .right_stream(),
+        }
+    }
+
+    fn polling_root_stream(
+        &self,
+        start_block: u64,
+        end_block: Option<u64>,
+    ) -> impl Stream<Item = TreeChangedLog> + '_ {
+        self.block_stream_from(start_block, end_block)
+            .buffered(self.scan_concurrency)
+            .take_while(move |window| {
+                if let Err(error) = window {
+                    tracing::error!(
+                        %error,
+                        chain_id = self.chain_id,
+                        "Failed to fetch logs, ending the block stream"
+                    );
+                }
+                future::ready(window.is_ok())
+            })
+            .flat_map(move |window| {
+                let fut = async move {
+                    let (logs, to_block): (Vec<Log>, u64) =
+                        window.expect("failed windows end the stream");
+                    let events = stream::iter(
+                        logs.into_iter()
+                            .filter_map(|log| decode_tree_changed(&log)),
+                    );
+                    // Only polled once every event of the window was handled
+                    let checkpoint = stream::once(async move {
+                        self.mark_synced(to_block);
+                        None::<TreeChangedLog>
+                    })
+                    .filter_map(future::ready);
+                    events.chain(checkpoint)
+                };
+                fut.into_stream().flatten()
+            })
+    }
+
+    /// Catches up to the chain head by polling, then follows a websocket log
+    /// subscription. Falls back to polling if the subscription drops.
+    fn subscription_root_stream(
+        &self,
+        ws_endpoint: Url,
+    ) -> impl Stream<Item = TreeChangedLog> + '_ {
+        let fut = async move {
+            let (provider, subscription, head) = match self
+                .subscribe_logs(&ws_endpoint)
+                .await
+            {
+                Ok(subscription) => subscription,
+                Err(error) => {
+                    tracing::warn!(
+                        %error,
+                        chain_id = self.chain_id,
+                        "Failed to subscribe to logs, falling back to polling"
+                    );
+                    return self
+                        .polling_root_stream(self.resume_block(), None)
+                        .right_stream();
+                }
             };
-            fut.into_stream().flatten()
-        })
+
+            let last_seen_block = Arc::new(AtomicU64::new(head));
+            let catch_up =
+                self.polling_root_stream(self.resume_block(), Some(head));
+
+            let live = {
+                let last_seen_block = last_seen_block.clone();
+                subscription.into_stream().filter_map(move |log| {
+                    // The subscription is only serviced while the provider is alive
+                    let _provider = &provider;
+                    if let Some(block_number) = log.block_number {
+                        last_seen_block
+                            .fetch_max(block_number, Ordering::Relaxed);
+                        // Every earlier block has been handled by now
+                        self.mark_synced(block_number.saturating_sub(1));
+                    }
+                    future::ready(decode_tree_changed(&log))
+                })
+            };
+
+            let fallback = stream::once(async move {
+                // Rescan the last seen block in case it was only partially delivered
+                let from_block = last_seen_block.load(Ordering::Relaxed);
+                tracing::warn!(
+                    chain_id = self.chain_id,
+                    from_block,
+                    "Log subscription dropped, falling back to polling"
+                );
+                self.polling_root_stream(from_block, None)
+            })
+            .flatten();
+
+            catch_up.chain(live).chain(fallback).left_stream()
+        };
+
+        stream::once(fut).flatten()
     }
+
+    /// Subscribes to logs matching the filter, returning the subscription and
+    /// the chain head at the time of subscribing.
+    async fn subscribe_logs(
+        &self,
+        ws_endpoint: &Url,
+    ) -> Result<(RootProvider<PubSubFrontend>, Subscription<Log>, u64)> {
+        let provider = ProviderBuilder::new()
+            .on_ws(WsConnect::new(ws_endpoint.clone()))
+            .await?;
+        let subscription = provider.subscribe_logs(&self.filter).await?;
+        let head = provider.get_block_number().await?;
+
+        Ok((provider, subscription, head))
+    }
+}
+
+/// The number of the newest block at the commitment.
+async fn head_block<T, P>(
+    provider: Arc<P>,
+    finality: BlockNumberOrTag,
+) -> TransportResult<u64>
+where
+    T: Transport + Clone,
+    P: Provider<T>,
+{
+    if finality == BlockNumberOrTag::Latest {
+        return provider.get_block_number().await;
+    }
+
+    let block = provider
+        .get_block_by_number(finality, BlockTransactionsKind::Hashes)
+        .await?
+        .ok_or_else(|| {
+            TransportError::local_usage_str(&format!(
+                "no {finality} block is available"
+            ))
+        })?;
+    Ok(block.header.number)
+}
+
+impl From<Finality> for BlockNumberOrTag {
+    fn from(finality: Finality) -> Self {
+        match finality {
+            Finality::Latest => Self::Latest,
+            Finality::Safe => Self::Safe,
+            Finality::Finalized => Self::Finalized,
+        }
+    }
+}
+
+/// Fetches the logs matching `filter` in the block range, halving the range
+/// whenever the provider rejects the query as too large.
+async fn get_logs_split<T, P>(
+    provider: Arc<P>,
+    filter: Arc<Filter>,
+    from_block: u64,
+    to_block: u64,
+) -> TransportResult<Vec<Log>>
+where
+    T: Transport + Clone,
+    P: Provider<T>,
+{
+    let mut logs = Vec::new();
+    // Ranges still to fetch, the earliest last so logs stay in order
+    let mut ranges = vec![(from_block, to_block)];
+
+    while let Some((from_block, to_block)) = ranges.pop() {
+        let window = filter
+            .as_ref()
+            .clone()
+            .from_block(BlockNumberOrTag::from(from_block))
+            .to_block(BlockNumberOrTag::from(to_block));
+
+        match provider.get_logs(&window).await {
+            Ok(window_logs) => logs.extend(window_logs),
+            Err(error)
+                if from_block < to_block && is_window_too_large(&error) =>
+            {
+                let mid = from_block + (to_block - from_block) / 2;
+                tracing::warn!(
+                    %error,
+                    from_block,
+                    to_block,
+                    "Log query too large, halving the window, consider lowering window_size"
+                );
+                ranges.push((mid + 1, to_block));
+                ranges.push((from_block, mid));
+            }
+            Err(error) => return Err(error),
+        }
+    }
+
+    Ok(logs)
+}
+
+/// Whether the provider rejected a log query for matching too many logs or
+/// spanning too many blocks.
+fn is_window_too_large(error: &TransportError) -> bool {
+    const PATTERNS: &[&str] = &[
+        "query returned more than",
+        "block range too large",
+        "block range is too large",
+        "range too large",
+        "exceed maximum block range",
+        "response size exceeded",
+        "too many results",
+        "log response size exceeded",
+        "limit exceeded",
+    ];
+
+    let message = error.to_string().to_lowercase();
+    PATTERNS.iter().any(|pattern| message.contains(pattern))
+}
+
+/// A `TreeChanged` event along with the transaction that emitted it
+#[derive(Debug, Clone)]
+pub struct TreeChangedLog {
+    pub event: TreeChanged,
+    pub transaction_hash: Option<B256>,
+}
+
+fn decode_tree_changed(log: &Log) -> Option<TreeChangedLog> {
+    TreeChanged::decode_log(&log.inner, false)
+        .ok()
+        .map(|l| TreeChangedLog {
+            event: l.data,
+            transaction_hash: log.transaction_hash,
+        })
 }
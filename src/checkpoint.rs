@@ -0,0 +1,52 @@
+use std::path::{Path, PathBuf};
+
+/// Persists the last block the scanner has fully processed so a restart can
+/// resume from it.
+#[derive(Debug, Clone)]
+pub struct Checkpoint {
+    path: PathBuf,
+}
+
+impl Checkpoint {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Reads the checkpointed block, or `None` if the checkpoint is missing
+    /// or corrupt.
+    pub fn read(&self) -> Option<u64> {
+        let contents = match std::fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                return None;
+            }
+            Err(error) => {
+                tracing::warn!(path = %self.path.display(), %error, "Failed to read checkpoint");
+                return None;
+            }
+        };
+
+        match contents.trim().parse() {
+            Ok(block) => Some(block),
+            Err(error) => {
+                tracing::warn!(path = %self.path.display(), %error, "Ignoring corrupt checkpoint");
+                None
+            }
+        }
+    }
+
+    /// Atomically replaces the checkpoint with the given block.
+    pub fn write(&self, block: u64) {
+        let tmp = self.path.with_extension("tmp");
+        let result = std::fs::write(&tmp, block.to_string())
+            .and_then(|()| std::fs::rename(&tmp, &self.path));
+
+        if let Err(error) = result {
+            tracing::warn!(path = %self.path.display(), block, %error, "Failed to write checkpoint");
+        }
+    }
+}
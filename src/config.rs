@@ -1,45 +1,171 @@
 use core::fmt;
-use std::path::Path;
+use std::collections::{BTreeMap, HashSet};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use alloy::network::EthereumWallet;
-use alloy::primitives::Address;
+use alloy::primitives::{Address, U256};
 use alloy::providers::fillers::{
-    BlobGasFiller, CachedNonceManager, ChainIdFiller, GasFiller, JoinFill,
-    NonceFiller,
+    BlobGasFiller, ChainIdFiller, GasFiller, JoinFill, NonceFiller,
 };
-use alloy::providers::{Provider, ProviderBuilder};
-use alloy::rpc::client::ClientBuilder;
-use alloy::transports::http::Http;
-use alloy::transports::layers::{RetryBackoffLayer, RetryBackoffService};
-use reqwest::Client;
+use alloy::providers::{Provider, ProviderBuilder, RootProvider, WsConnect};
+use alloy::rpc::client::{ClientBuilder, RpcClient};
+use alloy::transports::http::reqwest::header::{
+    HeaderMap, HeaderName, HeaderValue,
+};
+use alloy::transports::http::{Client, Http};
+use alloy::transports::layers::RetryBackoffLayer;
+use alloy::transports::BoxTransport;
+use eyre::eyre::{bail, eyre};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use telemetry_batteries::reexports::metrics::Label;
 use url::Url;
 
-use crate::relay::signer::{AlloySignerProvider, TxFillers};
+use crate::block_scanner::{AdaptiveWindow, WindowSizer};
+use crate::failover::FailoverTransport;
+use crate::relay::signer::{
+    AlloySignerProvider, RelayNonceManager, TxFillers, TxOverrides,
+};
+use crate::relay::TreeChangeKind;
+
+/// A [`FailoverTransport`], retrying rate limited requests with backoff unless
+/// the retry layer is disabled
+pub type ThrottledTransport = BoxTransport;
 
-pub type ThrottledTransport = RetryBackoffService<Http<Client>>;
+/// How long fetching a remote configuration may take
+pub const CONFIG_FETCH_TIMEOUT: Duration = Duration::from_secs(30);
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+/// Compute units charged for an `eth_getLogs` request by typical providers
+pub const GET_LOGS_COMPUTE_UNITS: u64 = 75;
+
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
 pub struct Config {
-    /// The network from which roots will be propagated
-    pub canonical_network: CanonicalNetworkConfig,
+    /// The networks from which roots will be propagated, a single
+    /// `canonical_network` table is accepted as well
+    #[serde(alias = "canonical_network", deserialize_with = "one_or_many")]
+    #[schemars(with = "OneOrMany<CanonicalNetworkConfig>")]
+    pub canonical_networks: Vec<CanonicalNetworkConfig>,
     /// The networks to which roots will be propagated
     #[serde(default)]
     pub bridged_networks: Vec<BridgedNetworkConfig>,
     #[serde(default)]
     pub telemetry: Option<TelemetryConfig>,
+    /// The health and event stream server
+    #[serde(default)]
+    pub server: Option<ServerConfig>,
+    #[serde(default)]
+    pub alerting: Option<AlertingConfig>,
+    /// The deployment region, attached to all metrics and logs
+    #[serde(default)]
+    pub region: Option<String>,
+    /// Bounds the propagations in flight at once across all bridged
+    /// networks, unbounded if unset
+    #[serde(default)]
+    pub max_concurrent_propagations: Option<usize>,
+    /// File to which roots that failed to propagate are appended as JSON
+    /// lines, for `retry-dead-letters` to re-propagate
+    #[serde(default)]
+    pub dead_letter_path: Option<PathBuf>,
 }
 
 impl Config {
-    pub fn load(config_path: Option<impl AsRef<Path>>) -> eyre::Result<Self> {
+    /// Loads the configuration from an optional file, layering environment
+    /// overrides on top.
+    ///
+    /// The file format is inferred from the extension unless `format` is set.
+    pub fn load(
+        config_path: Option<impl AsRef<Path>>,
+        format: Option<ConfigFormat>,
+    ) -> eyre::Result<Self> {
         dotenv::dotenv().ok();
         let mut settings = config::Config::builder();
 
         if let Some(path) = config_path {
-            settings = settings
-                .add_source(config::File::from(path.as_ref()).required(true));
+            let mut file = config::File::from(path.as_ref()).required(true);
+            if let Some(format) = format {
+                file = file.format(format.into());
+            }
+            settings = settings.add_source(file);
         }
 
+        Self::from_builder(settings)
+    }
+
+    /// Loads the configuration from a string in the given format, layering
+    /// environment overrides on top.
+    pub fn load_from_str(
+        contents: &str,
+        format: ConfigFormat,
+    ) -> eyre::Result<Self> {
+        dotenv::dotenv().ok();
+        let settings = config::Config::builder()
+            .add_source(config::File::from_str(contents, format.into()));
+
+        Self::from_builder(settings)
+    }
+
+    /// Reads the configuration, such as stdin, to the end and loads it in the
+    /// given format.
+    pub fn load_from_reader(
+        reader: impl std::io::Read,
+        format: ConfigFormat,
+    ) -> eyre::Result<Self> {
+        let contents = std::io::read_to_string(reader)?;
+        Self::load_from_str(&contents, format)
+    }
+
+    /// Fetches the configuration from an `http(s)://` URL, layering
+    /// environment overrides on top.
+    ///
+    /// The format is inferred from the extension of the URL path unless
+    /// `format` is set.
+    pub async fn load_from_url(
+        url: &Url,
+        format: Option<ConfigFormat>,
+    ) -> eyre::Result<Self> {
+        // Query strings and credentials may carry access tokens
+        let mut shown = url.clone();
+        shown.set_query(None);
+        let _ = shown.set_password(None);
+
+        let format = format
+            .or_else(|| {
+                Path::new(url.path())
+                    .extension()
+                    .and_then(|ext| ConfigFormat::from_extension(ext.to_str()?))
+            })
+            .ok_or_else(|| {
+                eyre!("Cannot infer the format of the config at {shown}, set --config-format")
+            })?;
+
+        let response = reqwest::Client::new()
+            .get(url.clone())
+            .timeout(CONFIG_FETCH_TIMEOUT)
+            .send()
+            .await
+            .map_err(|e| {
+                eyre!(
+                    "Failed to fetch config from {shown}: {}",
+                    e.without_url()
+                )
+            })?;
+        let status = response.status();
+        if !status.is_success() {
+            bail!("Fetching config from {shown} failed with status {status}");
+        }
+        let contents = response.text().await.map_err(|e| {
+            eyre!("Failed to read config from {shown}: {}", e.without_url())
+        })?;
+
+        Self::load_from_str(&contents, format)
+            .map_err(|e| eyre!("Invalid config fetched from {shown}: {e}"))
+    }
+
+    fn from_builder(
+        settings: config::ConfigBuilder<config::builder::DefaultState>,
+    ) -> eyre::Result<Self> {
         let settings = settings
             .add_source(
                 config::Environment::default()
@@ -48,52 +174,657 @@ impl Config {
             )
             .build()?;
 
-        let config = serde_path_to_error::deserialize(settings)?;
+        let mut config: Self = serde_path_to_error::deserialize(settings)?;
+        config.load_secrets()?;
+        config.validate()?;
 
         Ok(config)
     }
+
+    /// The canonical network whose roots are propagated to the bridged
+    /// network, if it can be resolved.
+    pub fn canonical_for(
+        &self,
+        bridged: &BridgedNetworkConfig,
+    ) -> Option<&CanonicalNetworkConfig> {
+        match &bridged.canonical {
+            Some(name) => self
+                .canonical_networks
+                .iter()
+                .find(|canonical| &canonical.name == name),
+            None if self.canonical_networks.len() == 1 => {
+                self.canonical_networks.first()
+            }
+            None => None,
+        }
+    }
+
+    /// Reads the wallet secrets configured as file paths.
+    fn load_secrets(&mut self) -> eyre::Result<()> {
+        for canonical in &mut self.canonical_networks {
+            if let Some(wallet) = &mut canonical.wallet {
+                wallet.load_secrets(&canonical.name)?;
+            }
+        }
+        for bridged in &mut self.bridged_networks {
+            if let Some(wallet) = &mut bridged.wallet {
+                wallet.load_secrets(&bridged.name)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks the semantic correctness of the configuration.
+    pub fn validate(&self) -> eyre::Result<()> {
+        if self.canonical_networks.is_empty() {
+            bail!("At least one canonical network must be configured");
+        }
+
+        let mut canonical_names = HashSet::new();
+        for canonical in &self.canonical_networks {
+            if !canonical_names.insert(canonical.name.as_str()) {
+                bail!(
+                    "Canonical network {}: network names must be unique",
+                    canonical.name
+                );
+            }
+            if canonical.world_id_addr.is_zero() {
+                bail!(
+                    "Canonical network {}: world_id_addr must not be the zero address",
+                    canonical.name
+                );
+            }
+            canonical.provider.validate(&canonical.name)?;
+            canonical.validate_scan_range()?;
+            if let Some(wallet) = &canonical.wallet {
+                wallet.validate(&canonical.name)?;
+            }
+        }
+
+        if self.bridged_networks.is_empty() {
+            bail!("At least one bridged network must be configured");
+        }
+        if !self.bridged_networks.iter().any(|bridged| bridged.enabled) {
+            bail!("At least one bridged network must be enabled");
+        }
+        if self.max_concurrent_propagations == Some(0) {
+            bail!("max_concurrent_propagations must be greater than zero");
+        }
+
+        let mut names = HashSet::new();
+        for bridged in &self.bridged_networks {
+            if !names.insert(bridged.name.as_str()) {
+                bail!(
+                    "Bridged network {}: network names must be unique",
+                    bridged.name
+                );
+            }
+
+            bridged.provider.validate(&bridged.name)?;
+
+            if bridged.state_bridge_addrs.is_empty() {
+                bail!(
+                    "Bridged network {}: at least one state_bridge_addr is required",
+                    bridged.name
+                );
+            }
+            let mut state_bridges = HashSet::new();
+            for state_bridge in &bridged.state_bridge_addrs {
+                if state_bridge.is_zero() {
+                    bail!(
+                        "Bridged network {}: state_bridge_addr must not be the zero address",
+                        bridged.name
+                    );
+                }
+                if *state_bridge == bridged.world_id_addr {
+                    bail!(
+                        "Bridged network {}: state_bridge_addr and world_id_addr must differ",
+                        bridged.name
+                    );
+                }
+                if !state_bridges.insert(state_bridge) {
+                    bail!(
+                        "Bridged network {}: state bridge {state_bridge} is listed twice",
+                        bridged.name
+                    );
+                }
+            }
+            if bridged.world_id_addr.is_zero() {
+                bail!(
+                    "Bridged network {}: world_id_addr must not be the zero address",
+                    bridged.name
+                );
+            }
+
+            if let Some(key) = bridged
+                .tags
+                .keys()
+                .find(|key| RESERVED_TAGS.contains(&key.as_str()))
+            {
+                bail!(
+                    "Bridged network {}: tag {key} is reserved",
+                    bridged.name
+                );
+            }
+
+            for &kind in bridged.propagate_kinds.iter().flatten() {
+                if let TreeChangeKind::Unknown(kind) =
+                    TreeChangeKind::from(kind)
+                {
+                    bail!(
+                        "Bridged network {}: unknown tree change kind {kind} in propagate_kinds",
+                        bridged.name
+                    );
+                }
+            }
+
+            if let Some(replacement) = &bridged.replacement {
+                // Nodes reject replacements that raise fees by less than 10%
+                if replacement.fee_bump_percent < 10 {
+                    bail!(
+                        "Bridged network {}: fee_bump_percent must be at least 10",
+                        bridged.name
+                    );
+                }
+            }
+
+            let Some(canonical) = self.canonical_for(bridged) else {
+                match &bridged.canonical {
+                    Some(name) => bail!(
+                        "Bridged network {}: no canonical network named {name}",
+                        bridged.name
+                    ),
+                    None => bail!(
+                        "Bridged network {}: canonical must be set when several canonical networks are configured",
+                        bridged.name
+                    ),
+                }
+            };
+
+            match (&bridged.wallet, &canonical.wallet) {
+                (Some(wallet), _) => wallet.validate(&bridged.name)?,
+                (None, Some(_)) => {}
+                (None, None) => bail!(
+                    "Bridged network {}: no wallet configured and no global wallet to fall back to",
+                    bridged.name
+                ),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Either a single value or a list of values
+#[derive(Deserialize, JsonSchema)]
+#[serde(untagged)]
+enum OneOrMany<T> {
+    One(T),
+    Many(Vec<T>),
+}
+
+/// Accepts either a single value or a list of values.
+fn one_or_many<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(value) => vec![value],
+        OneOrMany::Many(values) => values,
+    })
+}
+
+/// The serialization format of a configuration source
+#[derive(Clone, Copy, Debug, clap::ValueEnum)]
+pub enum ConfigFormat {
+    Toml,
+    Yaml,
+    Json,
 }
 
-#[derive(Clone, Serialize, Deserialize)]
+impl ConfigFormat {
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension {
+            "toml" => Some(Self::Toml),
+            "yaml" | "yml" => Some(Self::Yaml),
+            "json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+}
+
+impl From<ConfigFormat> for config::FileFormat {
+    fn from(format: ConfigFormat) -> Self {
+        match format {
+            ConfigFormat::Toml => config::FileFormat::Toml,
+            ConfigFormat::Yaml => config::FileFormat::Yaml,
+            ConfigFormat::Json => config::FileFormat::Json,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, JsonSchema)]
 pub struct BridgedNetworkConfig {
     /// The wallet configuration for the network
     /// overrides the global wallet configuration
     pub wallet: Option<WalletConfig>,
-    pub state_bridge_addr: Address,
+    /// The name of the canonical network whose roots are propagated,
+    /// required when several canonical networks are configured
+    #[serde(default)]
+    pub canonical: Option<String>,
+    /// The state bridges roots are propagated through, in order. Several
+    /// may be given for networks routing roots along more than one path
+    #[serde(rename = "state_bridge_addr", deserialize_with = "one_or_many")]
+    #[schemars(with = "OneOrMany<String>")]
+    pub state_bridge_addrs: Vec<Address>,
+    #[schemars(with = "String")]
     pub world_id_addr: Address,
     #[serde(rename = "type")]
     pub ty: NetworkType,
     pub name: String,
+    /// Disabled networks are kept in the configuration but not relayed to
+    #[serde(default = "default::enabled")]
+    pub enabled: bool,
     pub provider: ProviderConfig,
+    /// The maximum total fee in wei (gas limit × gas price) a single
+    /// propagation may spend when signing locally, costlier propagations are
+    /// skipped and retried later
+    #[serde(default)]
+    #[schemars(with = "Option<String>")]
+    pub max_tx_fee_wei: Option<U256>,
+    /// How to reconnect the L2 provider after transport failures
+    #[serde(default)]
+    pub reconnect: ReconnectConfig,
+    /// How to restart the relay after it fails
+    #[serde(default)]
+    pub restart: RestartConfig,
+    /// How to confirm that a propagated root was delivered to the L2
+    #[serde(default)]
+    pub confirmation: ConfirmationConfig,
+    /// The minimum number of seconds between propagations, roots received in
+    /// the meantime are coalesced into the most recent one
+    #[serde(default)]
+    pub min_propagation_interval_secs: Option<u64>,
+    /// How to replace locally signed propagations that are stuck unmined
+    #[serde(default)]
+    pub replacement: Option<ReplacementConfig>,
+    /// When to pause propagation after consecutive failures
+    #[serde(default)]
+    pub circuit_breaker: Option<CircuitBreakerConfig>,
+    /// The `TreeChanged` kinds whose roots are propagated, all if unset
+    #[serde(default)]
+    pub propagate_kinds: Option<Vec<u8>>,
+    /// Simulate locally signed propagations with `eth_call` and skip those
+    /// that would revert instead of spending gas on them
+    #[serde(default)]
+    pub simulate: bool,
+    /// The kind of bridge carrying roots to the network
+    #[serde(default)]
+    pub bridge: BridgeKind,
+    /// How long to wait for a Polygon checkpoint to deliver a propagated
+    /// root before warning, checkpoints commonly take over half an hour
+    #[serde(default = "default::polygon_checkpoint_timeout_secs")]
+    pub polygon_checkpoint_timeout_secs: u64,
+    /// Seconds after which a single propagation is abandoned, including
+    /// waiting for it to be mined
+    #[serde(default = "default::propagation_timeout_secs")]
+    pub propagation_timeout_secs: u64,
+    /// Check at startup that a contract is deployed at `state_bridge_addr` on
+    /// the canonical chain
+    #[serde(default)]
+    pub verify_state_bridge: bool,
+    /// Propagate the latest canonical root when the relay starts if the
+    /// network's root differs, instead of waiting for the next tree change.
+    /// Skipped if the canonical network gates its roots, see
+    /// [`CanonicalNetworkConfig::gates_roots`]
+    #[serde(default = "default::reconcile_on_startup")]
+    pub reconcile_on_startup: bool,
+    /// Tags attached to this relay's metrics as labels and to its spans,
+    /// alongside the global telemetry `service_name`
+    #[serde(default)]
+    pub tags: BTreeMap<String, String>,
+}
+
+/// Label keys set by the relay itself, which tags may not override
+pub const RESERVED_TAGS: &[&str] = &["network", "chain_id", "status", "region"];
+
+impl BridgedNetworkConfig {
+    /// The labels of this relay's metrics, its name and configured tags.
+    pub fn metric_labels(&self) -> Vec<Label> {
+        std::iter::once(Label::new("network", self.name.clone()))
+            .chain(
+                self.tags
+                    .iter()
+                    .map(|(key, value)| Label::new(key.clone(), value.clone())),
+            )
+            .collect()
+    }
+
+    /// The configured tags in Datadog's `key:value,...` form, if any.
+    pub fn span_tags(&self) -> Option<String> {
+        (!self.tags.is_empty()).then(|| {
+            self.tags
+                .iter()
+                .map(|(key, value)| format!("{key}:{value}"))
+                .collect::<Vec<_>>()
+                .join(",")
+        })
+    }
 }
 
 impl fmt::Debug for BridgedNetworkConfig {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("BridgedNetworkConfig")
-            .field("state_bridge_addr", &self.state_bridge_addr)
+            .field("canonical", &self.canonical)
+            .field("state_bridge_addrs", &self.state_bridge_addrs)
             .field("world_id_addr", &self.world_id_addr)
             .field("ty", &self.ty)
             .field("name", &self.name)
+            .field("enabled", &self.enabled)
             .field("provider", &self.provider)
+            .field("max_tx_fee_wei", &self.max_tx_fee_wei)
+            .field("reconnect", &self.reconnect)
+            .field("restart", &self.restart)
+            .field("confirmation", &self.confirmation)
+            .field(
+                "min_propagation_interval_secs",
+                &self.min_propagation_interval_secs,
+            )
+            .field("replacement", &self.replacement)
+            .field("circuit_breaker", &self.circuit_breaker)
+            .field("propagate_kinds", &self.propagate_kinds)
+            .field("simulate", &self.simulate)
+            .field("bridge", &self.bridge)
+            .field(
+                "polygon_checkpoint_timeout_secs",
+                &self.polygon_checkpoint_timeout_secs,
+            )
+            .field("propagation_timeout_secs", &self.propagation_timeout_secs)
+            .field("verify_state_bridge", &self.verify_state_bridge)
+            .field("reconcile_on_startup", &self.reconcile_on_startup)
+            .field("tags", &self.tags)
             .finish()
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ReconnectConfig {
+    /// The initial backoff in milliseconds before rebuilding the provider
+    #[serde(default = "default::reconnect_initial_backoff_ms")]
+    pub initial_backoff_ms: u64,
+    /// The maximum backoff in seconds between reconnection attempts
+    #[serde(default = "default::reconnect_max_backoff_secs")]
+    pub max_backoff_secs: u64,
+    /// The number of reconnection attempts before the relay gives up
+    #[serde(default = "default::reconnect_max_attempts")]
+    pub max_attempts: u32,
+}
+
+impl Default for ReconnectConfig {
+    fn default() -> Self {
+        Self {
+            initial_backoff_ms: default::reconnect_initial_backoff_ms(),
+            max_backoff_secs: default::reconnect_max_backoff_secs(),
+            max_attempts: default::reconnect_max_attempts(),
+        }
+    }
+}
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum BridgeKind {
+    /// OP-stack bridges deliver roots within a few L1 blocks
+    #[default]
+    OpStack,
+    /// The Polygon FxPortal only delivers roots once the next checkpoint has
+    /// been submitted to L1
+    Polygon,
+}
+
+/// The commitment a canonical block must reach before its roots are relayed.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Finality {
+    /// Relay roots as soon as their block is the chain head
+    #[default]
+    Latest,
+    /// Relay roots once their block is safe, unlikely to be reorged
+    Safe,
+    /// Relay roots once their block is finalized, which on Ethereum takes
+    /// around 13 minutes
+    Finalized,
+}
+
+/// How urgently the tx sitter should get propagations mined, trading fees
+/// against inclusion time.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum TxPriority {
+    Slowest,
+    Slow,
+    Regular,
+    #[default]
+    Fast,
+    Fastest,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum ConfirmationConfig {
+    /// Re-read the L2 `latestRoot` after the propagation backoff
+    #[default]
+    LatestRoot,
+    /// Watch the L2 for the `RootAdded` event of the propagated root
+    RootAdded {
+        /// Seconds to wait for the event before warning
+        #[serde(default = "default::root_added_timeout_secs")]
+        timeout_secs: u64,
+    },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ReplacementConfig {
+    /// Seconds to wait for a receipt before replacing the transaction
+    pub replacement_timeout_secs: u64,
+    /// The percentage by which both fees are raised on each replacement
+    #[serde(default = "default::fee_bump_percent")]
+    pub fee_bump_percent: u64,
+    /// The maximum number of replacements of a single propagation
+    #[serde(default = "default::max_fee_bumps")]
+    pub max_fee_bumps: u32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct CircuitBreakerConfig {
+    /// The number of consecutive failed propagations that opens the circuit
+    pub failure_threshold: u32,
+    /// Seconds to pause propagation for once the circuit is open
+    pub cooldown_secs: u64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct RestartConfig {
+    /// The initial backoff in milliseconds before restarting a failed relay or
+    /// scanner
+    #[serde(default = "default::restart_initial_backoff_ms")]
+    pub initial_backoff_ms: u64,
+    /// The maximum backoff in seconds between restarts
+    #[serde(default = "default::restart_max_backoff_secs")]
+    pub max_backoff_secs: u64,
+    /// The number of consecutive restarts before the relay or scanner is
+    /// abandoned
+    #[serde(default = "default::restart_max_restarts")]
+    pub max_restarts: u32,
+}
+
+impl Default for RestartConfig {
+    fn default() -> Self {
+        Self {
+            initial_backoff_ms: default::restart_initial_backoff_ms(),
+            max_backoff_secs: default::restart_max_backoff_secs(),
+            max_restarts: default::restart_max_restarts(),
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, JsonSchema)]
 pub struct CanonicalNetworkConfig {
+    #[schemars(with = "String")]
     pub world_id_addr: Address,
     /// The global wallet configuration
     pub wallet: Option<WalletConfig>,
     /// The number of blocks in the past to start scanning for new root events
     #[serde(default = "default::start_scan")]
     pub start_scan: u64,
+    /// The number of confirmations after which a block is considered safe
+    /// from reorgs, `0` disables reorg detection
+    #[serde(default)]
+    pub reorg_depth: u64,
+    /// File recording the last scanned block, scanning resumes from it on
+    /// restart instead of `start_scan` blocks in the past
+    #[serde(default)]
+    pub checkpoint_path: Option<PathBuf>,
+    /// Only scan blocks that have reached this commitment, so roots that may
+    /// still be reorged out are not relayed
+    #[serde(default, alias = "canonical_finality")]
+    pub finality: Finality,
+    /// Scan from exactly this block, overriding both `start_scan` and the
+    /// checkpoint, e.g. for backfills
+    #[serde(default)]
+    pub start_block: Option<u64>,
+    /// Stop scanning after this block, the relay exits once every root up
+    /// to it has been propagated
+    #[serde(default)]
+    pub end_block: Option<u64>,
+    /// How to restart the scanner after its stream ends
+    #[serde(default)]
+    pub restart: RestartConfig,
     #[serde(rename = "type")]
     pub ty: NetworkType,
     pub name: String,
     pub provider: ProviderConfig,
+    /// Independent RPC endpoints used to verify each root before it is
+    /// propagated
+    #[serde(default)]
+    pub verification_endpoints: Option<Vec<Url>>,
+    /// The number of verification endpoints that must recognize a root,
+    /// defaults to all of them
+    #[serde(default)]
+    pub verification_quorum: Option<usize>,
+    /// Only propagate insertion roots whose `registerIdentities` start index
+    /// falls within this range
+    #[serde(default)]
+    pub start_index_filter: Option<StartIndexRange>,
+    /// Milliseconds between the starts of the relays bridging this network,
+    /// spreading their startup reconciliation over time. Steady-state
+    /// propagation is unaffected
+    #[serde(default)]
+    pub startup_stagger_ms: u64,
+}
+
+/// Redacts the verification endpoints, which commonly embed API keys.
+impl fmt::Debug for CanonicalNetworkConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let verification_endpoints =
+            self.verification_endpoints.as_ref().map(|endpoints| {
+                endpoints
+                    .iter()
+                    .map(|endpoint| redact_url(endpoint.as_str()))
+                    .collect::<Vec<_>>()
+            });
+        f.debug_struct("CanonicalNetworkConfig")
+            .field("world_id_addr", &self.world_id_addr)
+            .field("wallet", &self.wallet)
+            .field("start_scan", &self.start_scan)
+            .field("reorg_depth", &self.reorg_depth)
+            .field("checkpoint_path", &self.checkpoint_path)
+            .field("finality", &self.finality)
+            .field("start_block", &self.start_block)
+            .field("end_block", &self.end_block)
+            .field("restart", &self.restart)
+            .field("ty", &self.ty)
+            .field("name", &self.name)
+            .field("provider", &self.provider)
+            .field("verification_endpoints", &verification_endpoints)
+            .field("verification_quorum", &self.verification_quorum)
+            .field("start_index_filter", &self.start_index_filter)
+            .field("startup_stagger_ms", &self.startup_stagger_ms)
+            .finish()
+    }
+}
+
+impl CanonicalNetworkConfig {
+    /// Whether roots are only relayed once final, verified or within the
+    /// start index range.
+    ///
+    /// Roots read from `latestRoot` rather than scanned bypass these checks.
+    pub fn gates_roots(&self) -> bool {
+        self.finality != Finality::Latest
+            || self.verification_endpoints.is_some()
+            || self.start_index_filter.is_some()
+    }
+
+    /// Checks that a bounded scan doesn't end before it starts.
+    pub fn validate_scan_range(&self) -> eyre::Result<()> {
+        if let (Some(start_block), Some(end_block)) =
+            (self.start_block, self.end_block)
+        {
+            if start_block > end_block {
+                bail!(
+                    "Canonical network {}: start_block {start_block} is after end_block {end_block}",
+                    self.name
+                );
+            }
+        }
+        Ok(())
+    }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+/// An inclusive range of identity tree leaf indices
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct StartIndexRange {
+    pub min: u32,
+    pub max: u32,
+}
+
+impl StartIndexRange {
+    pub fn contains(&self, index: u32) -> bool {
+        (self.min..=self.max).contains(&index)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum NetworkType {
     Evm,
@@ -101,17 +832,529 @@ pub enum NetworkType {
     Scroll,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case", tag = "type")]
 pub enum WalletConfig {
-    Mnemonic { mnemonic: String },
-    TxSitter { url: String, gas_limit: Option<u64> },
+    Mnemonic {
+        #[serde(default)]
+        mnemonic: String,
+        /// File holding the mnemonic, exclusive with `mnemonic`
+        #[serde(default)]
+        mnemonic_path: Option<PathBuf>,
+        /// The BIP-32 derivation index of the signing key
+        #[serde(default)]
+        index: u32,
+        /// Overrides the estimated gas limit of propagation transactions
+        #[serde(default)]
+        gas_limit: Option<u64>,
+        /// Overrides the estimated `maxFeePerGas` in wei
+        #[serde(default)]
+        max_fee_per_gas: Option<u128>,
+        /// Overrides the estimated `maxPriorityFeePerGas` in wei
+        #[serde(default)]
+        max_priority_fee_per_gas: Option<u128>,
+        /// Skips propagations while the network's gas price exceeds this many
+        /// gwei, skipped roots are retried a minute later unless a newer one
+        /// arrives first
+        #[serde(default)]
+        max_gas_price_gwei: Option<u64>,
+        /// Blocks deep the propagation receipt must be before it is final
+        #[serde(default = "default::confirmations")]
+        confirmations: u64,
+    },
+    PrivateKey {
+        /// Hex encoded private key, with or without the `0x` prefix
+        #[serde(default)]
+        private_key: String,
+        /// File holding the private key, exclusive with `private_key`
+        #[serde(default)]
+        private_key_path: Option<PathBuf>,
+        /// Overrides the estimated gas limit of propagation transactions
+        #[serde(default)]
+        gas_limit: Option<u64>,
+        /// Overrides the estimated `maxFeePerGas` in wei
+        #[serde(default)]
+        max_fee_per_gas: Option<u128>,
+        /// Overrides the estimated `maxPriorityFeePerGas` in wei
+        #[serde(default)]
+        max_priority_fee_per_gas: Option<u128>,
+        /// Skips propagations while the network's gas price exceeds this many
+        /// gwei, skipped roots are retried a minute later unless a newer one
+        /// arrives first
+        #[serde(default)]
+        max_gas_price_gwei: Option<u64>,
+        /// Blocks deep the propagation receipt must be before it is final
+        #[serde(default = "default::confirmations")]
+        confirmations: u64,
+    },
+    Keystore {
+        /// Path to an encrypted JSON V3 keystore file
+        path: PathBuf,
+        /// Name of the environment variable holding the keystore password
+        password_env: String,
+        /// Overrides the estimated gas limit of propagation transactions
+        #[serde(default)]
+        gas_limit: Option<u64>,
+        /// Overrides the estimated `maxFeePerGas` in wei
+        #[serde(default)]
+        max_fee_per_gas: Option<u128>,
+        /// Overrides the estimated `maxPriorityFeePerGas` in wei
+        #[serde(default)]
+        max_priority_fee_per_gas: Option<u128>,
+        /// Skips propagations while the network's gas price exceeds this many
+        /// gwei, skipped roots are retried a minute later unless a newer one
+        /// arrives first
+        #[serde(default)]
+        max_gas_price_gwei: Option<u64>,
+        /// Blocks deep the propagation receipt must be before it is final
+        #[serde(default = "default::confirmations")]
+        confirmations: u64,
+    },
+    TxSitter {
+        /// The tx sitter URL, which embeds its API key
+        #[serde(default)]
+        url: String,
+        /// File holding the tx sitter URL, exclusive with `url`
+        #[serde(default)]
+        url_path: Option<PathBuf>,
+        /// HTTP Basic auth username, for tx sitters behind an auth proxy
+        #[serde(default)]
+        username: Option<String>,
+        /// HTTP Basic auth password, required along with `username`
+        #[serde(default)]
+        password: String,
+        /// File holding the password, exclusive with `password`
+        #[serde(default)]
+        password_path: Option<PathBuf>,
+        /// Bearer token sent as the `Authorization` header, for tx sitters
+        /// behind an auth proxy. Exclusive with `username`
+        #[serde(default)]
+        auth_token: String,
+        /// File holding the bearer token, exclusive with `auth_token`
+        #[serde(default)]
+        auth_token_path: Option<PathBuf>,
+        gas_limit: Option<u64>,
+        /// Seconds to wait for the transaction to be mined before timing out
+        #[serde(default = "default::tx_timeout_secs")]
+        tx_timeout_secs: u64,
+        /// Seconds before the first transaction status poll, the interval
+        /// doubles after every poll up to `max_poll_interval_secs`
+        #[serde(default = "default::poll_interval_secs")]
+        poll_interval_secs: u64,
+        /// The maximum seconds between transaction status polls
+        #[serde(default = "default::max_poll_interval_secs")]
+        max_poll_interval_secs: u64,
+        /// Log a still pending transaction at info every this many polls.
+        /// Only the first pending poll and the timeout are logged otherwise
+        #[serde(default)]
+        pending_log_every: Option<u32>,
+        /// Cross-check mined transactions against their on-chain receipt
+        #[serde(default)]
+        verify_receipt: bool,
+        /// The priority propagations are sent with
+        #[serde(default)]
+        priority: TxPriority,
+    },
+    /// Proposes propagations to a Safe for its owners to confirm
+    Safe {
+        #[schemars(with = "String")]
+        safe_address: Address,
+        /// Base URL of the Safe Transaction Service for the chain
+        service_url: Url,
+        /// Hex encoded private key of a Safe owner or delegate
+        #[serde(default)]
+        proposer_key: String,
+        /// File holding the proposer key, exclusive with `proposer_key`
+        #[serde(default)]
+        proposer_key_path: Option<PathBuf>,
+    },
+}
+
+impl WalletConfig {
+    /// The overrides applied to transactions signed locally with this wallet.
+    pub fn tx_overrides(&self) -> TxOverrides {
+        match self {
+            Self::Mnemonic {
+                gas_limit,
+                max_fee_per_gas,
+                max_priority_fee_per_gas,
+                ..
+            }
+            | Self::PrivateKey {
+                gas_limit,
+                max_fee_per_gas,
+                max_priority_fee_per_gas,
+                ..
+            }
+            | Self::Keystore {
+                gas_limit,
+                max_fee_per_gas,
+                max_priority_fee_per_gas,
+                ..
+            } => TxOverrides {
+                gas_limit: *gas_limit,
+                max_fee_per_gas: *max_fee_per_gas,
+                max_priority_fee_per_gas: *max_priority_fee_per_gas,
+            },
+            Self::TxSitter { .. } | Self::Safe { .. } => TxOverrides::default(),
+        }
+    }
+
+    /// The gas price in wei above which locally signed propagations are
+    /// skipped, if any.
+    pub fn max_gas_price_wei(&self) -> Option<u128> {
+        match self {
+            Self::Mnemonic {
+                max_gas_price_gwei, ..
+            }
+            | Self::PrivateKey {
+                max_gas_price_gwei, ..
+            }
+            | Self::Keystore {
+                max_gas_price_gwei, ..
+            } => {
+                max_gas_price_gwei.map(|gwei| u128::from(gwei) * 1_000_000_000)
+            }
+            Self::TxSitter { .. } | Self::Safe { .. } => None,
+        }
+    }
+
+    /// The confirmations awaited for transactions signed locally with this
+    /// wallet.
+    pub fn confirmations(&self) -> u64 {
+        match self {
+            Self::Mnemonic { confirmations, .. }
+            | Self::PrivateKey { confirmations, .. }
+            | Self::Keystore { confirmations, .. } => *confirmations,
+            Self::TxSitter { .. } | Self::Safe { .. } => 1,
+        }
+    }
+
+    /// Replaces secrets configured as file paths with the trimmed contents
+    /// of the files, so the rest of the relay only sees inline values.
+    fn load_secrets(&mut self, network: &str) -> eyre::Result<()> {
+        match self {
+            Self::Mnemonic {
+                mnemonic,
+                mnemonic_path,
+                ..
+            } => load_secret(network, "mnemonic", mnemonic, mnemonic_path),
+            Self::PrivateKey {
+                private_key,
+                private_key_path,
+                ..
+            } => load_secret(
+                network,
+                "private_key",
+                private_key,
+                private_key_path,
+            ),
+            Self::TxSitter {
+                url,
+                url_path,
+                username,
+                password,
+                password_path,
+                auth_token,
+                auth_token_path,
+                ..
+            } => {
+                load_secret(network, "url", url, url_path)?;
+                if !auth_token.is_empty() || auth_token_path.is_some() {
+                    load_secret(
+                        network,
+                        "auth_token",
+                        auth_token,
+                        auth_token_path,
+                    )?;
+                }
+                if username.is_some() {
+                    load_secret(network, "password", password, password_path)
+                } else if !password.is_empty() || password_path.is_some() {
+                    bail!("Network {network}: password requires a username")
+                } else {
+                    Ok(())
+                }
+            }
+            Self::Safe {
+                proposer_key,
+                proposer_key_path,
+                ..
+            } => load_secret(
+                network,
+                "proposer_key",
+                proposer_key,
+                proposer_key_path,
+            ),
+            Self::Keystore { .. } => Ok(()),
+        }
+    }
+
+    fn validate(&self, network: &str) -> eyre::Result<()> {
+        if let Self::TxSitter {
+            url,
+            username,
+            password,
+            auth_token,
+            poll_interval_secs,
+            max_poll_interval_secs,
+            pending_log_every,
+            ..
+        } = self
+        {
+            if username.is_some() && !auth_token.is_empty() {
+                bail!(
+                    "Network {network}: username and auth_token are mutually exclusive"
+                );
+            }
+            if *pending_log_every == Some(0) {
+                bail!("Network {network}: pending_log_every must be positive");
+            }
+            tx_sitter_url(url, username.as_deref(), password).map_err(|e| {
+                eyre!("Network {network}: invalid tx sitter url: {e}")
+            })?;
+            if max_poll_interval_secs < poll_interval_secs {
+                bail!(
+                    "Network {network}: max_poll_interval_secs {max_poll_interval_secs} is below poll_interval_secs {poll_interval_secs}"
+                );
+            }
+        }
+
+        if self.max_gas_price_wei() == Some(0) {
+            bail!("Network {network}: max_gas_price_gwei must be positive");
+        }
+
+        let overrides = self.tx_overrides();
+        if let (Some(max_fee), Some(max_priority_fee)) = (
+            overrides.max_fee_per_gas,
+            overrides.max_priority_fee_per_gas,
+        ) {
+            if max_priority_fee > max_fee {
+                bail!(
+                    "Network {network}: max_priority_fee_per_gas {max_priority_fee} exceeds max_fee_per_gas {max_fee}"
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The tx sitter URL with the Basic auth credentials, if any, as its
+/// userinfo, which the client sends as an `Authorization` header.
+pub fn tx_sitter_url(
+    url: &str,
+    username: Option<&str>,
+    password: &str,
+) -> eyre::Result<Url> {
+    let mut url = Url::parse(url)?;
+    if let Some(username) = username {
+        url.set_username(username)
+            .and_then(|()| url.set_password(Some(password)))
+            .map_err(|()| {
+                eyre!(
+                    "url {} cannot carry credentials",
+                    redact_url(url.as_str())
+                )
+            })?;
+    }
+    Ok(url)
+}
+
+/// Resolves a secret given either inline or as the path of a file holding it.
+///
+/// The contents are never included in errors.
+fn load_secret(
+    network: &str,
+    field: &str,
+    secret: &mut String,
+    path: &mut Option<PathBuf>,
+) -> eyre::Result<()> {
+    match (secret.is_empty(), path.take()) {
+        (false, None) => {}
+        (false, Some(_)) => bail!(
+            "Network {network}: {field} and {field}_path are mutually exclusive"
+        ),
+        (true, None) => {
+            bail!(
+                "Network {network}: one of {field} or {field}_path is required"
+            )
+        }
+        (true, Some(path)) => {
+            let contents = std::fs::read_to_string(&path).map_err(|e| {
+                eyre!(
+                    "Network {network}: failed to read {field}_path {}: {e}",
+                    path.display()
+                )
+            })?;
+            *secret = contents.trim().to_owned();
+            if secret.is_empty() {
+                bail!(
+                    "Network {network}: {field}_path {} is empty",
+                    path.display()
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Describes the wallet without revealing any key material.
+impl fmt::Display for WalletConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Mnemonic { index, .. } => {
+                write!(f, "mnemonic (index {index})")
+            }
+            Self::PrivateKey { .. } => write!(f, "private key"),
+            Self::Keystore { path, .. } => {
+                write!(f, "keystore ({})", path.display())
+            }
+            Self::TxSitter { url, .. } => {
+                write!(f, "tx sitter ({})", redact_url(url))
+            }
+            Self::Safe { safe_address, .. } => {
+                write!(f, "safe proposer ({safe_address})")
+            }
+        }
+    }
+}
+
+const REDACTED: &str = "<redacted>";
+
+/// Redacts key material, the configuration is logged at startup.
+impl fmt::Debug for WalletConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Mnemonic {
+                mnemonic_path,
+                index,
+                gas_limit,
+                max_fee_per_gas,
+                max_priority_fee_per_gas,
+                max_gas_price_gwei,
+                confirmations,
+                ..
+            } => f
+                .debug_struct("Mnemonic")
+                .field("mnemonic", &REDACTED)
+                .field("mnemonic_path", mnemonic_path)
+                .field("index", index)
+                .field("gas_limit", gas_limit)
+                .field("max_fee_per_gas", max_fee_per_gas)
+                .field("max_priority_fee_per_gas", max_priority_fee_per_gas)
+                .field("max_gas_price_gwei", max_gas_price_gwei)
+                .field("confirmations", confirmations)
+                .finish(),
+            Self::PrivateKey {
+                private_key_path,
+                gas_limit,
+                max_fee_per_gas,
+                max_priority_fee_per_gas,
+                max_gas_price_gwei,
+                confirmations,
+                ..
+            } => f
+                .debug_struct("PrivateKey")
+                .field("private_key", &REDACTED)
+                .field("private_key_path", private_key_path)
+                .field("gas_limit", gas_limit)
+                .field("max_fee_per_gas", max_fee_per_gas)
+                .field("max_priority_fee_per_gas", max_priority_fee_per_gas)
+                .field("max_gas_price_gwei", max_gas_price_gwei)
+                .field("confirmations", confirmations)
+                .finish(),
+            Self::Keystore {
+                path,
+                password_env,
+                gas_limit,
+                max_fee_per_gas,
+                max_priority_fee_per_gas,
+                max_gas_price_gwei,
+                confirmations,
+            } => f
+                .debug_struct("Keystore")
+                .field("path", path)
+                .field("password_env", password_env)
+                .field("gas_limit", gas_limit)
+                .field("max_fee_per_gas", max_fee_per_gas)
+                .field("max_priority_fee_per_gas", max_priority_fee_per_gas)
+                .field("max_gas_price_gwei", max_gas_price_gwei)
+                .field("confirmations", confirmations)
+                .finish(),
+            Self::TxSitter {
+                url,
+                url_path,
+                username,
+                password_path,
+                auth_token_path,
+                gas_limit,
+                tx_timeout_secs,
+                poll_interval_secs,
+                max_poll_interval_secs,
+                pending_log_every,
+                verify_receipt,
+                priority,
+                ..
+            } => f
+                .debug_struct("TxSitter")
+                .field("url", &redact_url(url))
+                .field("url_path", url_path)
+                .field("username", username)
+                .field("password", &REDACTED)
+                .field("password_path", password_path)
+                .field("auth_token", &REDACTED)
+                .field("auth_token_path", auth_token_path)
+                .field("gas_limit", gas_limit)
+                .field("tx_timeout_secs", tx_timeout_secs)
+                .field("poll_interval_secs", poll_interval_secs)
+                .field("max_poll_interval_secs", max_poll_interval_secs)
+                .field("pending_log_every", pending_log_every)
+                .field("verify_receipt", verify_receipt)
+                .field("priority", priority)
+                .finish(),
+            Self::Safe {
+                safe_address,
+                service_url,
+                proposer_key_path,
+                ..
+            } => f
+                .debug_struct("Safe")
+                .field("safe_address", safe_address)
+                .field("service_url", service_url)
+                .field("proposer_key", &REDACTED)
+                .field("proposer_key_path", proposer_key_path)
+                .finish(),
+        }
+    }
+}
+
+/// Keeps only the scheme, host and port of a URL, as tx sitter and RPC URLs
+/// embed their API key in the path.
+pub fn redact_url(url: &str) -> String {
+    let Ok(url) = Url::parse(url) else {
+        return REDACTED.to_owned();
+    };
+    let port = url
+        .port()
+        .map(|port| format!(":{port}"))
+        .unwrap_or_default();
+    format!(
+        "{}://{}{port}/{REDACTED}",
+        url.scheme(),
+        url.host_str().unwrap_or_default()
+    )
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Clone, Deserialize, Serialize, JsonSchema)]
 pub struct ProviderConfig {
-    /// Ethereum RPC endpoint
-    pub rpc_endpoint: Url,
+    /// Ethereum RPC endpoint, or a list of endpoints failed over between in
+    /// order. A `ws://` or `wss://` first endpoint additionally enables log
+    /// subscriptions
+    #[serde(rename = "rpc_endpoint", deserialize_with = "one_or_many")]
+    #[schemars(with = "OneOrMany<Url>")]
+    pub rpc_endpoints: Vec<Url>,
     /// The maximum number of retries for rate limit errors
     #[serde(default = "default::max_rate_limit_retries")]
     pub max_rate_limit_retries: u32,
@@ -123,42 +1366,288 @@ pub struct ProviderConfig {
     pub compute_units_per_second: u64,
     #[serde(default = "default::window_size")]
     pub window_size: u64,
+    /// Enables adaptive window sizing, growing the window up to this size
+    /// while windows return few logs
+    #[serde(default)]
+    pub max_window_size: Option<u64>,
+    /// The number of logs per window the adaptive window size steers away from
+    #[serde(default = "default::window_log_threshold")]
+    pub window_log_threshold: u64,
+    /// The maximum number of concurrent `eth_getLogs` requests while scanning
+    #[serde(default = "default::scan_concurrency")]
+    pub scan_concurrency: usize,
+    /// Caps how many blocks per second are scanned while catching up to the
+    /// chain head, unlimited if unset
+    #[serde(default)]
+    pub max_blocks_per_second: Option<u64>,
+    /// The chain id the endpoint is expected to serve, checked at startup
+    #[serde(default)]
+    pub chain_id: Option<u64>,
+    /// Seconds after which an RPC request is abandoned, unlimited if unset
+    #[serde(default)]
+    pub request_timeout_secs: Option<u64>,
+    /// Headers sent with every RPC request, e.g. for providers that
+    /// authenticate with an API key header
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// Surfaces RPC errors immediately instead of retrying them with backoff,
+    /// for diagnosing failing or rate limiting endpoints
+    #[serde(default)]
+    pub disable_retry_layer: bool,
+    /// How locally signing wallets pick transaction nonces
+    #[serde(default)]
+    pub nonce_manager: NonceManagerKind,
+}
+
+/// How locally signing wallets pick transaction nonces.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum NonceManagerKind {
+    /// Fetch the nonce once and count up locally, saving a request per
+    /// transaction. Goes stale if another process sends with the same key
+    #[default]
+    Cached,
+    /// Fetch the nonce from the node before every transaction, for keys shared
+    /// between relay processes. Concurrent sends may still collide
+    Simple,
+}
+
+/// Redacts header values and endpoint paths, which commonly carry API keys.
+impl fmt::Debug for ProviderConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rpc_endpoints = self
+            .rpc_endpoints
+            .iter()
+            .map(|endpoint| redact_url(endpoint.as_str()))
+            .collect::<Vec<_>>();
+        let headers = self
+            .headers
+            .keys()
+            .map(|name| (name, REDACTED))
+            .collect::<BTreeMap<_, _>>();
+        f.debug_struct("ProviderConfig")
+            .field("rpc_endpoints", &rpc_endpoints)
+            .field("max_rate_limit_retries", &self.max_rate_limit_retries)
+            .field("initial_backoff", &self.initial_backoff)
+            .field("compute_units_per_second", &self.compute_units_per_second)
+            .field("window_size", &self.window_size)
+            .field("max_window_size", &self.max_window_size)
+            .field("window_log_threshold", &self.window_log_threshold)
+            .field("scan_concurrency", &self.scan_concurrency)
+            .field("max_blocks_per_second", &self.max_blocks_per_second)
+            .field("chain_id", &self.chain_id)
+            .field("request_timeout_secs", &self.request_timeout_secs)
+            .field("headers", &headers)
+            .field("disable_retry_layer", &self.disable_retry_layer)
+            .field("nonce_manager", &self.nonce_manager)
+            .finish()
+    }
 }
 
 impl ProviderConfig {
-    pub fn provider(&self) -> impl Provider<ThrottledTransport> {
-        let client = ClientBuilder::default()
-            .layer(RetryBackoffLayer::new(
-                self.max_rate_limit_retries,
-                self.initial_backoff,
-                self.compute_units_per_second,
-            ))
-            .http(self.rpc_endpoint.clone());
-        ProviderBuilder::new().on_client(client)
+    fn validate(&self, network: &str) -> eyre::Result<()> {
+        if self.rpc_endpoints.is_empty() {
+            bail!("Network {network}: at least one rpc_endpoint is required");
+        }
+        self.header_map()
+            .map_err(|e| eyre!("Network {network}: invalid rpc header: {e}"))?;
+        Ok(())
     }
 
-    pub fn signer(&self, wallet: EthereumWallet) -> AlloySignerProvider {
-        let client = ClientBuilder::default()
-            .layer(RetryBackoffLayer::new(
-                self.max_rate_limit_retries,
-                self.initial_backoff,
-                self.compute_units_per_second,
-            ))
-            .http(self.rpc_endpoint.clone());
+    fn header_map(&self) -> eyre::Result<HeaderMap> {
+        self.headers
+            .iter()
+            .map(|(name, value)| {
+                let mut value = HeaderValue::from_str(value)?;
+                value.set_sensitive(true);
+                Ok((HeaderName::from_bytes(name.as_bytes())?, value))
+            })
+            .collect()
+    }
+
+    /// The HTTP client RPC requests are sent with, applying the configured
+    /// timeout and headers.
+    pub fn http_client(&self) -> Client {
+        let mut builder = Client::builder().default_headers(
+            self.header_map()
+                .expect("headers are validated when the config is loaded"),
+        );
+        if let Some(timeout) = self.request_timeout_secs {
+            builder = builder.timeout(Duration::from_secs(timeout));
+        }
+        builder
+            .build()
+            .expect("failed to build the RPC HTTP client")
+    }
+
+    /// A provider over the primary endpoint, without failover or rate
+    /// limiting.
+    pub fn http_provider(&self) -> RootProvider<Http<Client>> {
+        let transport =
+            Http::with_client(self.http_client(), self.http_endpoint());
+        let is_local = transport.guess_local();
+        ProviderBuilder::new().on_client(RpcClient::new(transport, is_local))
+    }
+
+    /// The primary endpoint.
+    pub fn rpc_endpoint(&self) -> &Url {
+        &self.rpc_endpoints[0]
+    }
+
+    /// Checks that the endpoint serves the configured chain id, if any.
+    pub async fn verify_chain_id(&self, network: &str) -> eyre::Result<()> {
+        let Some(expected) = self.chain_id else {
+            return Ok(());
+        };
+
+        let actual = self.provider().get_chain_id().await?;
+        if actual != expected {
+            bail!(
+                "Network {network}: rpc endpoint serves chain id {actual}, expected {expected}"
+            );
+        }
 
+        Ok(())
+    }
+
+    /// Checks that a contract is deployed at the address.
+    pub async fn verify_deployed(
+        &self,
+        network: &str,
+        address: Address,
+    ) -> eyre::Result<()> {
+        let code = self.provider().get_code_at(address).await?;
+        if code.is_empty() {
+            bail!("Network {network}: no contract deployed at {address}");
+        }
+
+        Ok(())
+    }
+
+    /// The primary endpoint used for request/response calls.
+    ///
+    /// Websocket endpoints are mapped to the equivalent HTTP scheme.
+    pub fn http_endpoint(&self) -> Url {
+        http_endpoint(self.rpc_endpoint())
+    }
+
+    /// The endpoint used for subscriptions, if the primary endpoint is a
+    /// websocket.
+    pub fn ws_endpoint(&self) -> Option<Url> {
+        matches!(self.rpc_endpoint().scheme(), "ws" | "wss")
+            .then(|| self.rpc_endpoint().clone())
+    }
+
+    /// The number of concurrent log queries while scanning.
+    ///
+    /// The retry layer only backs off once the provider starts rate limiting,
+    /// so the configured concurrency is capped to what the compute unit budget
+    /// can sustain instead of relying on it.
+    pub fn effective_scan_concurrency(&self) -> usize {
+        let budget = (self.compute_units_per_second / GET_LOGS_COMPUTE_UNITS)
+            .max(1) as usize;
+        if self.scan_concurrency > budget {
+            tracing::warn!(
+                scan_concurrency = self.scan_concurrency,
+                compute_units_per_second = self.compute_units_per_second,
+                budget,
+                "Scan concurrency exceeds the compute unit budget, capping it"
+            );
+        }
+        self.scan_concurrency.clamp(1, budget)
+    }
+
+    /// Sizes the scan window, adaptively if a maximum window size is set.
+    pub fn window_sizer(&self) -> WindowSizer {
+        let adaptive = self.max_window_size.map(|max_size| AdaptiveWindow {
+            max_size: max_size.max(self.window_size),
+            log_threshold: self.window_log_threshold,
+        });
+        WindowSizer::new(self.window_size, adaptive)
+    }
+
+    pub fn provider(&self) -> RootProvider<ThrottledTransport> {
+        ProviderBuilder::new().on_client(self.client())
+    }
+
+    /// A rate limited provider kept connected to the primary endpoint if it
+    /// is a websocket, or failing over between the endpoints over HTTP
+    /// otherwise.
+    ///
+    /// Dropped websocket connections are re-established by the client, and
+    /// callers rebuild the provider once that gives up.
+    pub async fn read_provider(
+        &self,
+    ) -> eyre::Result<RootProvider<BoxTransport>> {
+        let Some(ws_endpoint) = self.ws_endpoint() else {
+            return Ok(ProviderBuilder::new().on_client(self.client()));
+        };
+        let ws = WsConnect::new(ws_endpoint);
+        let client = if self.disable_retry_layer {
+            ClientBuilder::default().ws(ws).await?.boxed()
+        } else {
+            ClientBuilder::default()
+                .layer(self.retry_layer())
+                .ws(ws)
+                .await?
+                .boxed()
+        };
+        Ok(ProviderBuilder::new().on_client(client))
+    }
+
+    pub fn signer(&self, wallet: EthereumWallet) -> AlloySignerProvider {
         ProviderBuilder::new()
-            .filler(Self::tx_fillers())
+            .filler(self.tx_fillers())
             .wallet(wallet)
-            .on_client(client)
+            .on_client(self.client())
     }
 
-    fn tx_fillers() -> TxFillers {
+    /// Builds a client failing over between the endpoints, rate limited
+    /// unless the retry layer is disabled.
+    fn client(&self) -> RpcClient<ThrottledTransport> {
+        let transport = FailoverTransport::new(
+            self.rpc_endpoints.iter().map(http_endpoint).collect(),
+            self.http_client(),
+        );
+        let is_local = transport.guess_local();
+        if self.disable_retry_layer {
+            return ClientBuilder::default()
+                .transport(transport, is_local)
+                .boxed();
+        }
+        ClientBuilder::default()
+            .layer(self.retry_layer())
+            .transport(transport, is_local)
+            .boxed()
+    }
+
+    fn retry_layer(&self) -> RetryBackoffLayer {
+        RetryBackoffLayer::new(
+            self.max_rate_limit_retries,
+            self.initial_backoff,
+            self.compute_units_per_second,
+        )
+    }
+
+    fn tx_fillers(&self) -> TxFillers {
         JoinFill::new(
             GasFiller,
             JoinFill::new(
                 BlobGasFiller,
                 JoinFill::new(
-                    NonceFiller::new(CachedNonceManager::default()),
+                    NonceFiller::new(RelayNonceManager::from(
+                        self.nonce_manager,
+                    )),
                     ChainIdFiller::default(),
                 ),
             ),
@@ -166,17 +1655,75 @@ impl ProviderConfig {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Maps websocket endpoints to the equivalent HTTP scheme.
+fn http_endpoint(endpoint: &Url) -> Url {
+    let mut endpoint = endpoint.clone();
+    let scheme = match endpoint.scheme() {
+        "ws" => "http",
+        "wss" => "https",
+        _ => return endpoint,
+    };
+    // Switching between special schemes is always valid
+    let _ = endpoint.set_scheme(scheme);
+    endpoint
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct TelemetryConfig {
     // Service name - used for logging, metrics and tracing
     pub service_name: String,
-    // Traces
+    /// Where traces are exported, Datadog unless set
+    #[serde(default)]
+    pub backend: TelemetryBackend,
+    // Traces, the Datadog agent or OpenTelemetry collector endpoint
     pub traces_endpoint: Option<String>,
+    /// Protocol spoken to the OpenTelemetry collector
+    #[serde(default)]
+    pub otlp_protocol: OtlpProtocol,
     // Metrics
     pub metrics: Option<MetricsConfig>,
+    // Prometheus scrape endpoint
+    pub prometheus: Option<PrometheusConfig>,
+}
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum TelemetryBackend {
+    #[default]
+    Datadog,
+    /// Export traces to an OpenTelemetry collector over OTLP
+    Otlp,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum OtlpProtocol {
+    #[default]
+    Grpc,
+    Http,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct MetricsConfig {
     pub host: String,
     pub port: u16,
@@ -185,8 +1732,51 @@ pub struct MetricsConfig {
     pub prefix: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PrometheusConfig {
+    /// The address to serve `GET /metrics` on
+    pub address: SocketAddr,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AlertingConfig {
+    /// The number of bridged networks that must be failing at the same time
+    /// before a single widespread outage alert is raised
+    pub widespread_outage_threshold: usize,
+}
+
+#[derive(Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ServerConfig {
+    /// The address to bind the server to
+    pub address: SocketAddr,
+    /// Bearer token guarding the admin endpoints, `POST /propagate/{network}`
+    /// and the like, which are disabled when unset
+    #[serde(default)]
+    pub admin_token: Option<String>,
+}
+
+impl fmt::Debug for ServerConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ServerConfig")
+            .field("address", &self.address)
+            .field(
+                "admin_token",
+                &self.admin_token.as_ref().map(|_| "<redacted>"),
+            )
+            .finish()
+    }
+}
+
 mod default {
 
+    pub const fn scan_concurrency() -> usize {
+        crate::block_scanner::DEFAULT_SCAN_CONCURRENCY
+    }
+
+    pub const fn window_log_threshold() -> u64 {
+        5000
+    }
+
     pub const fn window_size() -> u64 {
         1000
     }
@@ -206,4 +1796,295 @@ mod default {
     pub const fn start_scan() -> u64 {
         600
     }
+
+    pub const fn fee_bump_percent() -> u64 {
+        20
+    }
+
+    pub const fn max_fee_bumps() -> u32 {
+        3
+    }
+
+    pub const fn root_added_timeout_secs() -> u64 {
+        600
+    }
+
+    pub const fn confirmations() -> u64 {
+        1
+    }
+
+    pub const fn tx_timeout_secs() -> u64 {
+        120
+    }
+
+    pub const fn poll_interval_secs() -> u64 {
+        2
+    }
+
+    pub const fn reconcile_on_startup() -> bool {
+        true
+    }
+
+    pub const fn enabled() -> bool {
+        true
+    }
+
+    pub const fn max_poll_interval_secs() -> u64 {
+        30
+    }
+
+    pub const fn reconnect_initial_backoff_ms() -> u64 {
+        500
+    }
+
+    pub const fn reconnect_max_backoff_secs() -> u64 {
+        60
+    }
+
+    pub const fn reconnect_max_attempts() -> u32 {
+        10
+    }
+
+    pub const fn polygon_checkpoint_timeout_secs() -> u64 {
+        3 * 60 * 60
+    }
+
+    pub const fn propagation_timeout_secs() -> u64 {
+        600
+    }
+
+    pub const fn restart_initial_backoff_ms() -> u64 {
+        1000
+    }
+
+    pub const fn restart_max_backoff_secs() -> u64 {
+        300
+    }
+
+    pub const fn restart_max_restarts() -> u32 {
+        10
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::address;
+
+    use super::*;
+
+    const API_KEY: &str = "a1b2c3d4e5f6";
+    const MNEMONIC: &str =
+        "test test test test test test test test test test test junk";
+
+    /// A minimal configuration with `bridged` spliced into its only bridged
+    /// network.
+    fn config_toml(bridged: &str) -> String {
+        format!(
+            r#"
+            [canonical_network]
+            type = "evm"
+            name = "Canonical"
+            world_id_addr = "0xb2ead588f14e69266d1b87936b75325181377076"
+            provider = {{ rpc_endpoint = "http://127.0.0.1:8545" }}
+            wallet = {{ type = "mnemonic", mnemonic = "{MNEMONIC}" }}
+
+            [[bridged_networks]]
+            type = "evm"
+            name = "Bridged"
+            state_bridge_addr = "0x2F418Aa7D500B525EE8B80BB5F643A877ef82e09"
+            world_id_addr = "0xE177F37AF0A862A02edFEa4F59C02668E9d0aAA4"
+            provider = {{ rpc_endpoint = "http://127.0.0.1:9545" }}
+            {bridged}
+            "#
+        )
+    }
+
+    #[test]
+    fn accepts_enabled_networks() {
+        Config::load_from_str(&config_toml(""), ConfigFormat::Toml).unwrap();
+    }
+
+    #[test]
+    fn rejects_configs_without_enabled_networks() {
+        let error = Config::load_from_str(
+            &config_toml("enabled = false"),
+            ConfigFormat::Toml,
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "At least one bridged network must be enabled"
+        );
+    }
+
+    fn assert_loaded(config: &Config) {
+        assert_eq!(config.canonical_networks[0].name, "Canonical");
+        let bridged = &config.bridged_networks[0];
+        assert_eq!(bridged.name, "Bridged");
+        assert_eq!(
+            bridged.state_bridge_addrs,
+            vec![address!("2F418Aa7D500B525EE8B80BB5F643A877ef82e09")]
+        );
+        assert_eq!(
+            bridged.provider.rpc_endpoints,
+            vec!["http://127.0.0.1:9545".parse::<Url>().unwrap()]
+        );
+    }
+
+    #[test]
+    fn loads_toml_from_reader() {
+        let config = config_toml("");
+        let config =
+            Config::load_from_reader(config.as_bytes(), ConfigFormat::Toml)
+                .unwrap();
+        assert_loaded(&config);
+    }
+
+    #[test]
+    fn loads_yaml_from_reader() {
+        let config = format!(
+            r#"
+canonical_network:
+  type: evm
+  name: Canonical
+  world_id_addr: "0xb2ead588f14e69266d1b87936b75325181377076"
+  provider:
+    rpc_endpoint: "http://127.0.0.1:8545"
+  wallet:
+    type: mnemonic
+    mnemonic: "{MNEMONIC}"
+bridged_networks:
+  - type: evm
+    name: Bridged
+    state_bridge_addr: "0x2F418Aa7D500B525EE8B80BB5F643A877ef82e09"
+    world_id_addr: "0xE177F37AF0A862A02edFEa4F59C02668E9d0aAA4"
+    provider:
+      rpc_endpoint: "http://127.0.0.1:9545"
+"#
+        );
+        let config =
+            Config::load_from_reader(config.as_bytes(), ConfigFormat::Yaml)
+                .unwrap();
+        assert_loaded(&config);
+    }
+
+    #[test]
+    fn loads_json_from_reader() {
+        let config = serde_json::json!({
+            "canonical_network": {
+                "type": "evm",
+                "name": "Canonical",
+                "world_id_addr": "0xb2ead588f14e69266d1b87936b75325181377076",
+                "provider": { "rpc_endpoint": "http://127.0.0.1:8545" },
+                "wallet": { "type": "mnemonic", "mnemonic": MNEMONIC },
+            },
+            "bridged_networks": [{
+                "type": "evm",
+                "name": "Bridged",
+                "state_bridge_addr": "0x2F418Aa7D500B525EE8B80BB5F643A877ef82e09",
+                "world_id_addr": "0xE177F37AF0A862A02edFEa4F59C02668E9d0aAA4",
+                "provider": { "rpc_endpoint": "http://127.0.0.1:9545" },
+            }],
+        })
+        .to_string();
+        let config =
+            Config::load_from_reader(config.as_bytes(), ConfigFormat::Json)
+                .unwrap();
+        assert_loaded(&config);
+    }
+
+    #[test]
+    fn rejects_config_in_another_format() {
+        let config = config_toml("");
+        assert!(Config::load_from_reader(
+            config.as_bytes(),
+            ConfigFormat::Json
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn debug_redacts_endpoint_api_keys() {
+        let config = Config::load_from_str(
+            &format!(
+                r#"
+                [canonical_network]
+                type = "evm"
+                name = "Canonical"
+                world_id_addr = "0xb2ead588f14e69266d1b87936b75325181377076"
+                provider = {{ rpc_endpoint = "https://eth.example.com/v2/{API_KEY}" }}
+                verification_endpoints = ["https://verify.example.com/{API_KEY}"]
+                wallet = {{ type = "mnemonic", mnemonic = "{MNEMONIC}" }}
+
+                [[bridged_networks]]
+                type = "evm"
+                name = "Bridged"
+                state_bridge_addr = "0x2F418Aa7D500B525EE8B80BB5F643A877ef82e09"
+                world_id_addr = "0xE177F37AF0A862A02edFEa4F59C02668E9d0aAA4"
+                provider = {{ rpc_endpoint = ["https://op.example.com/{API_KEY}", "https://op-backup.example.com:8545/{API_KEY}"] }}
+                "#
+            ),
+            ConfigFormat::Toml,
+        )
+        .unwrap();
+
+        let debug = format!("{config:?}");
+
+        assert!(!debug.contains(API_KEY), "API key leaked: {debug}");
+        assert!(debug.contains("https://eth.example.com/<redacted>"));
+        assert!(debug.contains("https://verify.example.com/<redacted>"));
+        assert!(debug.contains("https://op-backup.example.com:8545/<redacted>"));
+    }
+
+    #[test]
+    fn loads_and_redacts_the_tx_sitter_auth_token() {
+        let mut token = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut token, b"s3cr3t-token\n").unwrap();
+        let config = Config::load_from_str(
+            &config_toml(&format!(
+                r#"wallet = {{ type = "tx_sitter", url = "https://tx-sitter.example/1/api/key", auth_token_path = "{}" }}"#,
+                token.path().display()
+            )),
+            ConfigFormat::Toml,
+        )
+        .unwrap();
+
+        let wallet = config.bridged_networks[0].wallet.as_ref().unwrap();
+        let WalletConfig::TxSitter { auth_token, .. } = wallet else {
+            panic!("not a tx sitter wallet: {wallet:?}");
+        };
+        assert_eq!(auth_token, "s3cr3t-token");
+        let debug = format!("{config:?}");
+        assert!(!debug.contains("s3cr3t-token"), "token leaked: {debug}");
+    }
+
+    #[test]
+    fn rejects_tx_sitter_auth_token_with_username() {
+        let error = Config::load_from_str(
+            &config_toml(
+                r#"wallet = { type = "tx_sitter", url = "https://tx-sitter.example/1/api/key", username = "relay", password = "hunter2", auth_token = "token" }"#,
+            ),
+            ConfigFormat::Toml,
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Network Bridged: username and auth_token are mutually exclusive"
+        );
+    }
+
+    #[test]
+    fn rejects_mnemonic_index_beyond_u32() {
+        let error = Config::load_from_str(
+            &config_toml(&format!(
+                r#"wallet = {{ type = "mnemonic", mnemonic = "{MNEMONIC}", index = 4294967296 }}"#
+            )),
+            ConfigFormat::Toml,
+        )
+        .unwrap_err();
+
+        assert!(error.to_string().contains("expected u32"), "{error}");
+    }
 }
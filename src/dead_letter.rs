@@ -0,0 +1,97 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use semaphore::Field;
+use serde::{Deserialize, Serialize};
+
+/// A root the relay gave up propagating.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DeadLetterEntry {
+    pub network: String,
+    pub root: Field,
+    /// Unix time at which the propagation failed
+    pub timestamp: u64,
+    /// The error the last propagation attempt failed with
+    pub error: String,
+}
+
+impl DeadLetterEntry {
+    pub fn new(network: &str, root: Field, error: &eyre::Report) -> Self {
+        Self {
+            network: network.to_owned(),
+            root,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            error: error.to_string(),
+        }
+    }
+}
+
+/// Records roots that failed to propagate as JSON lines, so they can be
+/// reconciled later.
+#[derive(Debug, Clone)]
+pub struct DeadLetters {
+    path: PathBuf,
+}
+
+impl DeadLetters {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Appends an entry, logging instead of failing if it can't be written.
+    pub fn append(&self, entry: &DeadLetterEntry) {
+        let result = serde_json::to_string(entry)
+            .map_err(std::io::Error::from)
+            .and_then(|line| {
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&self.path)?
+                    .write_all(format!("{line}\n").as_bytes())
+            });
+
+        if let Err(error) = result {
+            tracing::error!(path = %self.path.display(), network = %entry.network, root = %entry.root, %error, "Failed to record dead letter");
+        }
+    }
+
+    /// Reads every entry, none if the file is missing.
+    pub fn read(&self) -> eyre::Result<Vec<DeadLetterEntry>> {
+        let contents = match std::fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(Vec::new());
+            }
+            Err(error) => return Err(error.into()),
+        };
+
+        contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| Ok(serde_json::from_str(line)?))
+            .collect()
+    }
+
+    /// Atomically replaces the entries.
+    pub fn write(&self, entries: &[DeadLetterEntry]) -> eyre::Result<()> {
+        let mut contents = String::new();
+        for entry in entries {
+            contents.push_str(&serde_json::to_string(entry)?);
+            contents.push('\n');
+        }
+
+        let tmp = self.path.with_extension("tmp");
+        std::fs::write(&tmp, contents)?;
+        std::fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,56 @@
+use alloy::primitives::U256;
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+/// Capacity of the lifecycle event channel.
+///
+/// Slow subscribers will miss events rather than apply backpressure to the relays.
+pub const EVENT_CHANNEL_CAPACITY: usize = 1000;
+
+/// A lifecycle event emitted while detecting and propagating roots.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "snake_case", tag = "event")]
+pub enum RelayEvent {
+    /// A new root was observed on the canonical network
+    RootDetected { root: U256 },
+    /// A relay started propagating a root to its bridged network
+    PropagationStarted { network: String, root: U256 },
+    /// A root was successfully propagated to a bridged network
+    PropagationConfirmed { network: String, root: U256 },
+    /// A root failed to propagate to a bridged network
+    PropagationFailed {
+        network: String,
+        root: U256,
+        error: String,
+    },
+}
+
+/// Broadcasts [`RelayEvent`]s to any number of subscribers.
+#[derive(Clone, Debug)]
+pub struct RelayEvents {
+    tx: broadcast::Sender<RelayEvent>,
+}
+
+impl RelayEvents {
+    pub fn new() -> Self {
+        let (tx, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+        Self { tx }
+    }
+
+    /// Emit an event to all current subscribers.
+    ///
+    /// Events emitted while nobody is subscribed are dropped.
+    pub fn emit(&self, event: RelayEvent) {
+        let _ = self.tx.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<RelayEvent> {
+        self.tx.subscribe()
+    }
+}
+
+impl Default for RelayEvents {
+    fn default() -> Self {
+        Self::new()
+    }
+}
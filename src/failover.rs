@@ -0,0 +1,119 @@
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use alloy::rpc::json_rpc::{RequestPacket, ResponsePacket};
+use alloy::transports::http::{Client, Http};
+use alloy::transports::{TransportError, TransportFut};
+use telemetry_batteries::reexports::metrics;
+use tower::Service;
+use url::Url;
+
+/// Consecutive failed requests after which the next endpoint is used
+pub const FAILOVER_THRESHOLD: u32 = 3;
+
+/// An HTTP transport over a list of endpoints, sending every request to the
+/// active one and rotating to the next after [`FAILOVER_THRESHOLD`]
+/// consecutive transport failures.
+///
+/// Wrapped by the `RetryBackoffLayer`, so every retried attempt counts
+/// towards the threshold, including rate limited ones.
+#[derive(Clone)]
+pub struct FailoverTransport {
+    endpoints: Arc<[(Url, Http<Client>)]>,
+    active: Arc<AtomicUsize>,
+    failures: Arc<AtomicU32>,
+}
+
+impl FailoverTransport {
+    /// Sends requests to the endpoints with the given HTTP client.
+    pub fn new(endpoints: Vec<Url>, client: Client) -> Self {
+        assert!(!endpoints.is_empty(), "at least one endpoint is required");
+        let endpoints = endpoints
+            .into_iter()
+            .map(|url| (url.clone(), Http::with_client(client.clone(), url)))
+            .collect();
+
+        Self {
+            endpoints,
+            active: Arc::new(AtomicUsize::new(0)),
+            failures: Arc::new(AtomicU32::new(0)),
+        }
+    }
+
+    /// The endpoint requests are currently sent to.
+    pub fn active_endpoint(&self) -> &Url {
+        &self.endpoints[self.active.load(Ordering::Relaxed)].0
+    }
+
+    pub fn guess_local(&self) -> bool {
+        self.endpoints.iter().all(|(_, http)| http.guess_local())
+    }
+
+    fn record_success(&self, index: usize) {
+        if self.active.load(Ordering::Relaxed) == index {
+            self.failures.store(0, Ordering::Relaxed);
+        }
+    }
+
+    fn record_failure(&self, index: usize) {
+        // Failures of requests sent before a failover don't count against
+        // the new endpoint
+        if self.endpoints.len() < 2
+            || self.active.load(Ordering::Relaxed) != index
+        {
+            return;
+        }
+
+        let failures = self.failures.fetch_add(1, Ordering::Relaxed) + 1;
+        if failures < FAILOVER_THRESHOLD {
+            return;
+        }
+
+        let next = (index + 1) % self.endpoints.len();
+        if self
+            .active
+            .compare_exchange(index, next, Ordering::Relaxed, Ordering::Relaxed)
+            .is_ok()
+        {
+            self.failures.store(0, Ordering::Relaxed);
+            // Endpoint paths commonly embed API keys, only hosts are reported
+            let from = host(&self.endpoints[index].0);
+            let to = host(&self.endpoints[next].0);
+            tracing::warn!(%from, %to, failures, "Failing over to the next RPC endpoint");
+            metrics::counter!("rpc_failovers", "endpoint" => to).increment(1);
+        }
+    }
+}
+
+impl Service<RequestPacket> for FailoverTransport {
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(
+        &mut self,
+        _cx: &mut Context<'_>,
+    ) -> Poll<Result<(), Self::Error>> {
+        // HTTP transports are always ready
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: RequestPacket) -> Self::Future {
+        let this = self.clone();
+        Box::pin(async move {
+            let index = this.active.load(Ordering::Relaxed);
+            let mut http = this.endpoints[index].1.clone();
+            let result = http.call(request).await;
+            match result {
+                Ok(_) => this.record_success(index),
+                Err(_) => this.record_failure(index),
+            }
+            result
+        })
+    }
+}
+
+fn host(url: &Url) -> String {
+    url.host_str().unwrap_or_default().to_owned()
+}
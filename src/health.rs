@@ -0,0 +1,111 @@
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+use telemetry_batteries::reexports::metrics;
+
+/// Tracks which bridged networks are currently failing to propagate roots.
+///
+/// When the number of simultaneously failing networks reaches the configured
+/// threshold a single widespread outage alert is raised, rather than relying on
+/// the individual per-network errors to tell the story.
+#[derive(Debug)]
+pub struct NetworkHealth {
+    threshold: Option<usize>,
+    state: Mutex<HealthState>,
+}
+
+#[derive(Debug, Default)]
+struct HealthState {
+    failing: HashSet<String>,
+    outage: bool,
+}
+
+impl NetworkHealth {
+    pub fn new(threshold: Option<usize>) -> Self {
+        Self {
+            threshold,
+            state: Mutex::default(),
+        }
+    }
+
+    /// Marks the network as failing and raises the widespread outage alert
+    /// if this pushes the number of failing networks over the threshold.
+    pub fn record_failure(&self, network: &str) {
+        let mut state = self.state.lock().expect("health lock poisoned");
+        state.failing.insert(network.to_owned());
+        self.evaluate(&mut state);
+    }
+
+    /// Marks the network as healthy.
+    pub fn record_success(&self, network: &str) {
+        let mut state = self.state.lock().expect("health lock poisoned");
+        state.failing.remove(network);
+        self.evaluate(&mut state);
+    }
+
+    fn evaluate(&self, state: &mut HealthState) {
+        let Some(threshold) = self.threshold else {
+            return;
+        };
+
+        let failing = state.failing.len();
+        metrics::gauge!("failing_networks").set(failing as f64);
+
+        if failing >= threshold && !state.outage {
+            state.outage = true;
+            metrics::gauge!("widespread_outage").set(1.0);
+            tracing::error!(
+                failing,
+                threshold,
+                networks = ?state.failing,
+                "Widespread outage: too many networks failing to propagate roots"
+            );
+        } else if failing < threshold && state.outage {
+            state.outage = false;
+            metrics::gauge!("widespread_outage").set(0.0);
+            tracing::info!(failing, threshold, "Widespread outage resolved");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outage(health: &NetworkHealth) -> bool {
+        health.state.lock().unwrap().outage
+    }
+
+    #[test]
+    fn raises_outage_once_threshold_is_reached() {
+        let health = NetworkHealth::new(Some(3));
+        let networks = ["optimism", "base", "world-chain", "polygon"];
+
+        health.record_failure(networks[0]);
+        health.record_failure(networks[1]);
+        assert!(!outage(&health));
+
+        health.record_failure(networks[2]);
+        assert!(outage(&health));
+
+        // Repeated failures keep the single outage raised
+        health.record_failure(networks[2]);
+        health.record_failure(networks[3]);
+        assert!(outage(&health));
+        assert_eq!(health.state.lock().unwrap().failing.len(), 4);
+
+        health.record_success(networks[3]);
+        assert!(outage(&health));
+        health.record_success(networks[2]);
+        assert!(!outage(&health));
+    }
+
+    #[test]
+    fn never_raises_outage_without_threshold() {
+        let health = NetworkHealth::new(None);
+        for network in ["optimism", "base", "world-chain", "polygon"] {
+            health.record_failure(network);
+        }
+        assert!(!outage(&health));
+    }
+}
@@ -1,39 +1,87 @@
 pub mod abi;
 pub mod block_scanner;
+pub mod checkpoint;
 pub mod config;
+pub mod dead_letter;
+pub mod events;
+pub mod failover;
+pub mod health;
+pub mod otlp;
 pub mod relay;
+pub mod safe;
+pub mod server;
+pub mod start_index_filter;
+pub mod status;
 pub mod tx_sitter;
 pub mod utils;
+pub mod verifier;
 
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::future::Future;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use alloy::network::EthereumWallet;
-use alloy::primitives::U256;
+use alloy::primitives::Address;
 use alloy::providers::Provider;
 use alloy::rpc::types::Filter;
-use alloy::signers::local::MnemonicBuilder;
+use alloy::signers::local::{MnemonicBuilder, PrivateKeySigner};
 use alloy::sol_types::SolEvent;
 use alloy_signer_local::coins_bip39::English;
-use clap::Parser;
-use config::{NetworkType, WalletConfig};
-use eyre::eyre::{eyre, Result};
+use clap::{Parser, Subcommand, ValueEnum};
+use config::{
+    redact_url, tx_sitter_url, BridgedNetworkConfig, CanonicalNetworkConfig,
+    ConfigFormat, MetricsConfig, NetworkType, PrometheusConfig, ProviderConfig,
+    TelemetryBackend, TelemetryConfig, WalletConfig,
+};
+use eyre::eyre::{bail, eyre, Result};
+use futures::stream::FuturesUnordered;
 use futures::StreamExt;
-use relay::signer::{AlloySigner, Signer, TxSitterSigner};
-use relay::{EVMRelay, Relay, Relayer};
-use telemetry_batteries::metrics::statsd::StatsdBattery;
+use metrics_exporter_prometheus::PrometheusBuilder;
+use metrics_exporter_statsd::{StatsdBuilder, StatsdRecorder};
+use metrics_util::layers::FanoutBuilder;
+use relay::signer::{
+    AlloySigner, Propagation, SafeSigner, Signer, TxSitterSigner,
+};
+use relay::supervisor::supervise;
+use relay::{CanonicalWorldId, EVMRelay, Relayer, RootUpdate, TreeChangeKind};
+use telemetry_batteries::reexports::metrics::{self, Label};
 use telemetry_batteries::tracing::datadog::DatadogBattery;
 use telemetry_batteries::tracing::TracingShutdownHandle;
+use tokio::sync::broadcast::Sender;
+use tokio::sync::Semaphore;
 use tokio::task::JoinSet;
-use tracing::info;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, Instrument};
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::Layer;
+use tx_sitter::TxSitterClient;
+use url::Url;
 
-use self::abi::IWorldIDIdentityManager::TreeChanged;
-use self::block_scanner::BlockScanner;
+use self::abi::IWorldIDIdentityManager::{
+    IWorldIDIdentityManagerInstance, TreeChanged,
+};
+use self::block_scanner::{BlockScanner, TreeChangedLog};
+use self::checkpoint::Checkpoint;
 use self::config::Config;
+use self::dead_letter::DeadLetters;
+use self::events::{RelayEvent, RelayEvents};
+use self::health::NetworkHealth;
 use self::relay::signer::AlloySignerProvider;
+use self::safe::SafeServiceClient;
+use self::start_index_filter::StartIndexFilter;
+use self::status::RelayStatus;
+use self::utils::track_alive;
+use self::verifier::RootVerifier;
+
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+const GIT_SHA: &str = match option_env!("GIT_SHA") {
+    Some(git_sha) => git_sha,
+    None => "unknown",
+};
+const BUILD_TIMESTAMP: &str = env!("BUILD_TIMESTAMP");
 
 /// This service syncs the state of the World Tree and spawns a server that can deliver inclusion proofs for a given identity.
 #[derive(Parser, Debug)]
@@ -44,9 +92,78 @@ struct Opts {
     #[clap(short, long)]
     config: Option<PathBuf>,
 
+    /// Format of the configuration, inferred from the file extension if unset
+    #[clap(long, value_enum)]
+    config_format: Option<ConfigFormat>,
+
+    /// Read the configuration from stdin instead of a file
+    #[clap(long, conflicts_with = "config", requires = "config_format")]
+    config_stdin: bool,
+
+    /// Fetch the configuration from an `http(s)://` URL instead of a file
+    #[clap(long, conflicts_with_all = ["config", "config_stdin"])]
+    config_url: Option<Url>,
+
+    /// Validate the configuration, print a summary of it and exit
+    #[clap(long)]
+    check_config: bool,
+
+    /// Print the JSON Schema of the configuration and exit
+    #[clap(long, hide = true)]
+    print_config_schema: bool,
+
+    /// Override the canonical network's RPC endpoint
+    #[clap(long)]
+    canonical_rpc: Option<Url>,
+
+    /// Scan the canonical network from exactly this block
+    #[clap(long)]
+    start_block: Option<u64>,
+
+    /// Stop scanning the canonical network after this block, exiting once
+    /// every root up to it has been propagated
+    #[clap(long)]
+    end_block: Option<u64>,
+
+    /// Only relay to the named bridged networks, may be repeated or comma
+    /// separated
+    #[clap(long, value_delimiter = ',')]
+    only: Vec<String>,
+
+    /// Log root propagation transactions instead of sending them
+    #[clap(long)]
+    dry_run: bool,
+
     /// Set to disable colors in the logs
     #[clap(long)]
     no_ansi: bool,
+
+    /// Format of the logs when Datadog telemetry is not configured
+    #[clap(long, value_enum, default_value_t = LogFormat::Pretty)]
+    log_format: LogFormat,
+
+    #[clap(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum LogFormat {
+    Pretty,
+    Json,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Propagate the current canonical root to a single bridged network and exit
+    Propagate {
+        /// Name of the bridged network to propagate to
+        #[clap(long)]
+        network: String,
+    },
+    /// Re-propagate the roots recorded in the dead letter file and exit,
+    /// removing those that succeed. Roots recorded by a relay running
+    /// meanwhile may be lost, so stop it first
+    RetryDeadLetters,
 }
 
 #[tokio::main]
@@ -58,199 +175,1205 @@ pub async fn main() -> Result<()> {
         std::env::set_var("RUST_LOG", "info");
     }
     let opts = Opts::parse();
-    let config = Config::load(opts.config.as_deref())?;
-
-    let _tracing_shutdown_handle = if let Some(telemetry) = &config.telemetry {
-        let tracing_shutdown_handle = DatadogBattery::init(
-            telemetry.traces_endpoint.as_deref(),
-            &telemetry.service_name,
-            None,
-            true,
+    if opts.print_config_schema {
+        let schema = schemars::schema_for!(Config);
+        println!("{}", serde_json::to_string_pretty(&schema)?);
+        return Ok(());
+    }
+
+    let mut config = if opts.config_stdin {
+        // `requires` guarantees the format is present
+        let format = opts.config_format.expect("config format is required");
+        Config::load_from_reader(std::io::stdin(), format)?
+    } else if let Some(url) = &opts.config_url {
+        if !matches!(url.scheme(), "http" | "https") {
+            bail!("--config-url must be an http:// or https:// URL");
+        }
+        Config::load_from_url(url, opts.config_format).await?
+    } else {
+        Config::load(opts.config.as_deref(), opts.config_format)?
+    };
+
+    if let Some(canonical_rpc) = &opts.canonical_rpc {
+        let [canonical] = config.canonical_networks.as_mut_slice() else {
+            bail!("--canonical-rpc requires a single canonical network");
+        };
+        canonical.provider.rpc_endpoints = vec![canonical_rpc.clone()];
+    }
+
+    if let Some(start_block) = opts.start_block {
+        let [canonical] = config.canonical_networks.as_mut_slice() else {
+            bail!("--start-block requires a single canonical network");
+        };
+        canonical.start_block = Some(start_block);
+    }
+
+    if let Some(end_block) = opts.end_block {
+        let [canonical] = config.canonical_networks.as_mut_slice() else {
+            bail!("--end-block requires a single canonical network");
+        };
+        canonical.end_block = Some(end_block);
+    }
+
+    if opts.start_block.is_some() || opts.end_block.is_some() {
+        for canonical in &config.canonical_networks {
+            canonical.validate_scan_range()?;
+        }
+    }
+
+    if !opts.only.is_empty() {
+        retain_networks(&mut config, &opts.only)?;
+    }
+
+    if opts.check_config {
+        print_config_summary(&config);
+        return Ok(());
+    }
+
+    let fmt_layer = match opts.log_format {
+        LogFormat::Pretty => tracing_subscriber::fmt::layer()
+            .with_ansi(!opts.no_ansi)
+            .pretty()
+            .compact()
+            .boxed(),
+        LogFormat::Json => tracing_subscriber::fmt::layer()
+            .with_ansi(!opts.no_ansi)
+            .json()
+            .boxed(),
+    };
+
+    let mut _otlp_shutdown_handle = None;
+    let _tracing_shutdown_handle = match &config.telemetry {
+        Some(telemetry) => {
+            let tracing_shutdown_handle = match telemetry.backend {
+                TelemetryBackend::Datadog => DatadogBattery::init(
+                    telemetry.traces_endpoint.as_deref(),
+                    &telemetry.service_name,
+                    None,
+                    true,
+                ),
+                TelemetryBackend::Otlp => {
+                    let (otlp_layer, handle) =
+                        otlp::layer(telemetry, config.region.as_deref())?;
+                    _otlp_shutdown_handle = Some(handle);
+
+                    tracing_subscriber::registry()
+                        .with(fmt_layer)
+                        .with(otlp_layer)
+                        .with(tracing_subscriber::EnvFilter::from_default_env())
+                        .init();
+
+                    TracingShutdownHandle
+                }
+            };
+
+            init_metrics(telemetry, config.region.as_deref())?;
+
+            tracing_shutdown_handle
+        }
+        None => {
+            tracing_subscriber::registry()
+                .with(fmt_layer)
+                .with(tracing_subscriber::EnvFilter::from_default_env())
+                .init();
+
+            TracingShutdownHandle
+        }
+    };
+
+    let span = root_span(config.region.as_deref());
+
+    async move {
+        info!(
+            version = VERSION,
+            git_sha = GIT_SHA,
+            build_timestamp = BUILD_TIMESTAMP,
+            "Build info"
         );
+        metrics::gauge!(
+            "build_info",
+            "version" => VERSION,
+            "git_sha" => GIT_SHA,
+            "build_timestamp" => BUILD_TIMESTAMP,
+        )
+        .set(1.0);
 
-        if let Some(metrics_config) = &telemetry.metrics {
-            StatsdBattery::init(
-                &metrics_config.host,
-                metrics_config.port,
-                metrics_config.queue_size,
-                metrics_config.buffer_size,
-                Some(&metrics_config.prefix),
-            )?;
+        if let Some(canonical_rpc) = &opts.canonical_rpc {
+            tracing::warn!(
+                canonical_rpc = %redact_url(canonical_rpc.as_str()),
+                "Canonical RPC endpoint overridden from the command line"
+            );
         }
 
-        tracing_shutdown_handle
-    } else {
-        tracing_subscriber::registry()
-            .with(
-                tracing_subscriber::fmt::layer()
-                    .with_ansi(!opts.no_ansi)
-                    .pretty()
-                    .compact(),
-            )
-            .with(tracing_subscriber::EnvFilter::from_default_env())
-            .init();
+        match opts.command {
+            Some(Command::Propagate { network }) => {
+                propagate(config, &network, opts.dry_run).await
+            }
+            Some(Command::RetryDeadLetters) => {
+                retry_dead_letters(config, opts.dry_run).await
+            }
+            None => {
+                info!(?config, "Starting world-id-relay");
 
-        TracingShutdownHandle
+                run(config, opts.dry_run).await
+            }
+        }
+    }
+    .instrument(span)
+    .await
+}
+
+/// Drops every bridged network not named in `names`.
+fn retain_networks(config: &mut Config, names: &[String]) -> Result<()> {
+    for name in names {
+        if !config
+            .bridged_networks
+            .iter()
+            .any(|bridged| &bridged.name == name)
+        {
+            bail!("No bridged network named {name}");
+        }
+    }
+
+    config
+        .bridged_networks
+        .retain(|bridged| names.contains(&bridged.name));
+    Ok(())
+}
+
+/// Prints the parsed networks and the wallet each relay will sign with.
+fn print_config_summary(config: &Config) {
+    println!("Configuration is valid");
+    for canonical in &config.canonical_networks {
+        println!(
+            "Canonical network: {} ({:?}) world id {} via {}",
+            canonical.name,
+            canonical.ty,
+            canonical.world_id_addr,
+            canonical.provider.rpc_endpoint(),
+        );
+    }
+
+    for bridged in &config.bridged_networks {
+        let canonical = config.canonical_for(bridged);
+        let wallet = bridged
+            .wallet
+            .as_ref()
+            .or(canonical.and_then(|canonical| canonical.wallet.as_ref()))
+            .map_or_else(|| "none".to_owned(), ToString::to_string);
+        println!(
+            "Bridged network: {} ({:?}) from {} state bridges {} world id {} via {}, wallet: {wallet}{}",
+            bridged.name,
+            bridged.ty,
+            canonical.map_or("none", |canonical| canonical.name.as_str()),
+            bridged
+                .state_bridge_addrs
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", "),
+            bridged.world_id_addr,
+            bridged.provider.rpc_endpoint(),
+            if bridged.enabled { "" } else { " (disabled)" },
+        );
+    }
+}
+
+/// Installs the metrics recorder, fanning out to StatsD and Prometheus when
+/// both are configured. Every metric is tagged with the deployment region if
+/// configured.
+fn init_metrics(
+    telemetry: &TelemetryConfig,
+    region: Option<&str>,
+) -> Result<()> {
+    let mut fanout = FanoutBuilder::default();
+    let mut enabled = false;
+
+    if let Some(metrics_config) = &telemetry.metrics {
+        fanout = fanout.add_recorder(statsd_recorder(metrics_config, region)?);
+        enabled = true;
+    }
+
+    if let Some(prometheus) = &telemetry.prometheus {
+        let (recorder, exporter) =
+            prometheus_builder(prometheus, region).build()?;
+        tokio::spawn(async move {
+            if let Err(error) = exporter.await {
+                tracing::error!(?error, "Prometheus exporter failed");
+            }
+        });
+        fanout = fanout.add_recorder(recorder);
+        enabled = true;
+    }
+
+    if enabled {
+        metrics::set_global_recorder(fanout.build())
+            .map_err(|e| eyre!("Failed to install metrics recorder: {e}"))?;
+        metrics::describe_gauge!(
+            "last_propagated_root_timestamp",
+            metrics::Unit::Seconds,
+            "Unix time of the last confirmed root propagation per network, \
+             the root itself is logged alongside"
+        );
+        metrics::describe_counter!(
+            "tree_changed_total",
+            metrics::Unit::Count,
+            "Tree changes seen by the scanner per canonical chain and kind, \
+             whether or not their roots are propagated"
+        );
+        metrics::describe_counter!(
+            "propagation_reverted_total",
+            metrics::Unit::Count,
+            "Propagation transactions mined but reverted per network"
+        );
+        metrics::describe_counter!(
+            "root_propagation_skipped",
+            metrics::Unit::Count,
+            "Propagations skipped per network without failing, retried \
+             unless a newer root arrives first"
+        );
+        metrics::describe_counter!(
+            "propagation_skipped_high_gas_total",
+            metrics::Unit::Count,
+            "Propagations skipped per network as the gas price exceeded the \
+             wallet's max_gas_price_gwei"
+        );
+        metrics::describe_histogram!(
+            "propagation_gas_cost_wei",
+            "Fee paid by each mined propagation transaction per network, \
+             gas used times the effective gas price"
+        );
+        metrics::describe_gauge!(
+            "task_alive",
+            metrics::Unit::Count,
+            "1 while a scanner, relay or server task is running and 0 once \
+             it has exited, per task and network"
+        );
+        metrics::describe_gauge!(
+            "propagation_permits_held",
+            metrics::Unit::Count,
+            "Propagations currently holding one of the \
+             max_concurrent_propagations permits"
+        );
+        metrics::describe_gauge!(
+            "scanner_lag_blocks",
+            metrics::Unit::Count,
+            "Blocks between the chain head and the last block scanned per \
+             canonical chain"
+        );
+    }
+
+    Ok(())
+}
+
+/// The span every log event inherits the region from.
+fn root_span(region: Option<&str>) -> tracing::Span {
+    tracing::info_span!("world_id_relay", region)
+}
+
+fn prometheus_builder(
+    prometheus: &PrometheusConfig,
+    region: Option<&str>,
+) -> PrometheusBuilder {
+    let builder =
+        PrometheusBuilder::new().with_http_listener(prometheus.address);
+    match region {
+        Some(region) => builder.add_global_label("region", region),
+        None => builder,
+    }
+}
+
+fn statsd_recorder(
+    metrics_config: &MetricsConfig,
+    region: Option<&str>,
+) -> Result<StatsdRecorder> {
+    let mut builder =
+        StatsdBuilder::from(&metrics_config.host, metrics_config.port)
+            .with_queue_size(metrics_config.queue_size)
+            .with_buffer_size(metrics_config.buffer_size);
+
+    if let Some(region) = region {
+        builder = builder.with_default_tag("region", region);
+    }
+
+    Ok(builder.build(Some(&metrics_config.prefix))?)
+}
+
+pub async fn run(config: Config, dry_run: bool) -> Result<()> {
+    run_with_shutdown(config, dry_run, CancellationToken::new()).await
+}
+
+/// Runs the relay until a task fails or `shutdown` is cancelled, then stops
+/// every relay before returning.
+pub async fn run_with_shutdown(
+    config: Config,
+    dry_run: bool,
+    shutdown: CancellationToken,
+) -> Result<()> {
+    let events = RelayEvents::new();
+    let status = RelayStatus::new();
+    let server = config.server.clone();
+    let effective_config = format!("{config:#?}");
+
+    // Each canonical network broadcasts its roots to the relays bridging it
+    let mut senders = HashMap::new();
+    let mut scanners = FuturesUnordered::new();
+    for canonical in &config.canonical_networks {
+        let (tx, _) = tokio::sync::broadcast::channel::<RootUpdate>(1000);
+        let scanner =
+            init_scanner(canonical.clone(), tx.clone(), events.clone())
+                .await?
+                .instrument(tracing::info_span!(
+                    "scanner",
+                    network = %canonical.name
+                ));
+        scanners.push(track_alive(
+            vec![
+                Label::new("task", "scanner"),
+                Label::new("network", canonical.name.clone()),
+            ],
+            scanner,
+        ));
+        senders.insert(canonical.name.clone(), tx);
+    }
+
+    // Relays bridging the same canonical network start one stagger apart
+    let mut started = HashMap::<&str, u32>::new();
+    let sources = config
+        .bridged_networks
+        .iter()
+        .filter(|bridged| bridged.enabled)
+        .map(|bridged| {
+            let span = tracing::info_span!(
+                "relay",
+                network = %bridged.name,
+                tags = bridged.span_tags()
+            );
+            let canonical = config.canonical_for(bridged).ok_or_else(|| {
+                eyre!("No canonical network for {}", bridged.name)
+            })?;
+            let position = started.entry(canonical.name.as_str()).or_default();
+            let delay =
+                Duration::from_millis(canonical.startup_stagger_ms) * *position;
+            *position += 1;
+            Ok((bridged.name.clone(), canonical.name.clone(), delay, span))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let relayers = init_relays(config, &events, &status, dry_run).await?;
+    let mut joinset = JoinSet::new();
+    // Shared with the server for manual propagation
+    let mut relays = HashMap::new();
+    for (relay, (network, source, delay, span)) in
+        relayers.into_iter().zip(sources)
+    {
+        let relay = Arc::new(relay);
+        // Subscribed before any delay, so no root is missed
+        let rx = senders[&source].subscribe();
+        let supervised = supervise(relay.clone(), rx);
+        let labels = vec![
+            Label::new("task", "relay"),
+            Label::new("network", network.clone()),
+        ];
+        joinset.spawn(
+            track_alive(labels, async move {
+                if !delay.is_zero() {
+                    tracing::info!(?delay, "Staggering relay startup");
+                    tokio::time::sleep(delay).await;
+                }
+                supervised.await
+            })
+            .instrument(span),
+        );
+        relays.insert(network, relay);
+    }
+    // The root streams close once their scanners finish, stopping the relays
+    drop(senders);
+
+    // Bounded scanners finish once they pass their end block, the service
+    // then stops when their relays have drained the remaining roots
+    let scanners_fut = async {
+        while let Some(res) = scanners.next().await {
+            if let Err(error) = res {
+                tracing::error!(%error, "Scanner task failed");
+                return Err(error);
+            }
+        }
+        // Finished cleanly, exiting is left to the relay drain
+        std::future::pending().await
+    };
+
+    let server_fut = async {
+        match server {
+            Some(server) => {
+                track_alive(
+                    vec![Label::new("task", "server")],
+                    server::serve(
+                        server,
+                        effective_config,
+                        events.clone(),
+                        status.clone(),
+                        relays,
+                    ),
+                )
+                .await
+            }
+            None => std::future::pending().await,
+        }
     };
 
-    info!(?config, "Starting world-id-relay");
+    let result = tokio::select! {
+        res = scanners_fut => res,
+        res = drain_relays(&mut joinset) => {
+            tracing::info!("Every relay stopped, exiting");
+            res
+        }
+        // The server only returns if it failed
+        res = server_fut => {
+            let error = res
+                .err()
+                .unwrap_or_else(|| eyre!("Server stopped unexpectedly"));
+            tracing::error!(%error, "Server task failed");
+            Err(error)
+        }
+        _ = shutdown.cancelled() => {
+            tracing::info!("Shutdown requested, stopping relays");
+            Ok(())
+        }
+    };
 
-    run(config).await
+    joinset.shutdown().await;
+    result
 }
 
-pub async fn run(config: Config) -> Result<()> {
-    let provider = Arc::new(config.canonical_network.provider.provider());
+/// Sets up the scanner for a canonical network.
+///
+/// The returned future broadcasts every root that passes the configured
+/// filters, restarting the scanner with backoff whenever its stream ends. It
+/// only fails once the stream ends too many times in a row, and only
+/// completes once a bounded scanner has passed its end block.
+async fn init_scanner(
+    canonical: CanonicalNetworkConfig,
+    tx: Sender<RootUpdate>,
+    events: RelayEvents,
+) -> Result<impl Future<Output = Result<()>>> {
+    canonical.provider.verify_chain_id(&canonical.name).await?;
+    let provider = Arc::new(canonical.provider.provider());
     let chain_id = provider.get_chain_id().await?;
 
     let latest_block_number = provider.get_block_number().await?;
 
     // // Start in the past by approximately 2 hours
-    let start_block_number = latest_block_number
-        .checked_sub(config.canonical_network.start_scan)
+    let mut start_block_number = latest_block_number
+        .checked_sub(canonical.start_scan)
         .unwrap_or_default();
 
+    // Resuming from the checkpoint covers any downtime longer than `start_scan`
+    let checkpoint = canonical.checkpoint_path.as_ref().map(Checkpoint::new);
+    if let Some(checkpoint) = &checkpoint {
+        match checkpoint.read() {
+            Some(block) if block < latest_block_number => {
+                tracing::info!(
+                    network = %canonical.name,
+                    path = %checkpoint.path().display(),
+                    block,
+                    "Resuming from checkpoint"
+                );
+                start_block_number = block + 1;
+            }
+            Some(block) => tracing::warn!(
+                network = %canonical.name,
+                path = %checkpoint.path().display(),
+                block,
+                latest_block_number,
+                "Checkpoint is ahead of the chain, ignoring it"
+            ),
+            None => {}
+        }
+    }
+
+    if let Some(start_block) = canonical.start_block {
+        tracing::warn!(
+            network = %canonical.name,
+            start_block,
+            computed_start_block = start_block_number,
+            latest_block_number,
+            "Start block overridden, scanning from it"
+        );
+        start_block_number = start_block;
+    }
+
     let filter = Filter::new()
-        .address(config.canonical_network.world_id_addr)
+        .address(canonical.world_id_addr)
         .event_signature(TreeChanged::SIGNATURE_HASH);
 
     let scanner = BlockScanner::new(
         provider.clone(),
-        config.canonical_network.provider.window_size,
+        canonical.provider.window_sizer(),
         start_block_number,
         filter,
+        canonical.reorg_depth,
+        canonical.provider.ws_endpoint(),
+        checkpoint,
     )
-    .await?;
+    .await?
+    .with_scan_concurrency(canonical.provider.effective_scan_concurrency())
+    .with_max_blocks_per_second(canonical.provider.max_blocks_per_second)
+    .with_finality(canonical.finality)
+    .with_end_block(canonical.end_block);
 
-    tracing::info!(chain_id, latest_block_number, "Starting ingestion");
+    tracing::info!(
+        network = %canonical.name,
+        chain_id,
+        latest_block_number,
+        end_block = canonical.end_block,
+        "Starting ingestion"
+    );
 
-    let (tx, _) = tokio::sync::broadcast::channel::<U256>(1000);
-    let relayers = init_relays(config)?;
-    let mut joinset = JoinSet::new();
-    for relay in relayers {
-        let tx = tx.clone();
-        joinset.spawn(async move {
-            relay.subscribe_roots(tx.subscribe()).await.map_err(|error| {
-                match relay {
-                    Relayer::EVMRelay(EVMRelay {
-                        signer: _,
-                        world_id_address,
-                        provider,
-                    }) => {
-                        tracing::error!(
-                            %error,
-                            %provider,
-                            %world_id_address,
-                            "Error subscribing to roots"
-                        );
-                    }
-                    Relayer::SvmRelay(_) => {
-                        tracing::error!(%error, "Error subscribing to roots");
+    let verifier = canonical.verification_endpoints.as_ref().map(|endpoints| {
+        RootVerifier::new(
+            canonical.world_id_addr,
+            endpoints,
+            canonical.verification_quorum,
+        )
+    });
+    let start_index_filter = canonical.start_index_filter.map(|range| {
+        StartIndexFilter::new(range, canonical.provider.http_provider())
+    });
+
+    let restart = canonical.restart;
+    Ok(async move {
+        let initial_backoff = Duration::from_millis(restart.initial_backoff_ms);
+        let max_backoff = Duration::from_secs(restart.max_backoff_secs);
+        let mut backoff = initial_backoff;
+        let mut restarts = 0;
+
+        loop {
+            let started = Instant::now();
+            scanner
+                .root_stream()
+                .for_each(|event| {
+                    let tx = &tx;
+                    let events = &events;
+                    let verifier = verifier.as_ref();
+                    let start_index_filter = start_index_filter.as_ref();
+                    async move {
+                        forward_root(
+                            &event,
+                            tx,
+                            events,
+                            start_index_filter,
+                            verifier,
+                            chain_id,
+                        )
+                        .await
                     }
-                }
-                eyre!(error)
-            })?;
-            Ok::<(), eyre::Report>(())
-        });
+                })
+                .await;
+
+            if scanner.finished() {
+                tracing::info!(
+                    end_block = scanner.end_block,
+                    "Scanned through the end block, stopping the scanner"
+                );
+                return Ok(());
+            }
+
+            // A stream that ran for a while ended for a new reason
+            if started.elapsed() >= max_backoff {
+                restarts = 0;
+                backoff = initial_backoff;
+            }
+            if restarts >= restart.max_restarts {
+                bail!("Scanner stream ended {restarts} times in a row");
+            }
+            restarts += 1;
+            tracing::warn!(
+                restarts,
+                ?backoff,
+                "Scanner stream ended, restarting"
+            );
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(max_backoff);
+        }
+    })
+}
+
+/// Sends a root observed on the canonical network to the relays, unless the
+/// start index filter drops it or it fails independent verification.
+async fn forward_root(
+    event: &TreeChangedLog,
+    tx: &Sender<RootUpdate>,
+    events: &RelayEvents,
+    start_index_filter: Option<&StartIndexFilter>,
+    verifier: Option<&RootVerifier>,
+    chain_id: u64,
+) {
+    let field = event.event.postRoot;
+    events.emit(RelayEvent::RootDetected { root: field });
+    // Counted before filtering, for capacity planning
+    let kind = TreeChangeKind::from(event.event.kind);
+    metrics::counter!(
+        "tree_changed_total",
+        "kind" => kind.label(),
+        "chain_id" => chain_id.to_string(),
+    )
+    .increment(1);
+    if let Some(filter) = start_index_filter {
+        if !filter.allows(event).await {
+            return;
+        }
+    }
+    if let Some(verifier) = verifier {
+        if !verifier.verify(field).await {
+            return;
+        }
+    }
+    if let Err(e) = tx.send(RootUpdate::from(&event.event)) {
+        tracing::error!(?e, "Error sending root");
     }
+}
 
-    let scanner_fut = async {
-        scanner
-            .root_stream()
-            .for_each(|event| {
-                let tx = tx.clone();
-                async move {
-                    let field = event.postRoot;
-                    if let Err(e) = tx.send(field) {
-                        tracing::error!(?e, "Error sending root");
-                    }
-                }
-            })
-            .await;
+/// Logs relays that have given up restarting or stopped, completing once
+/// none are left.
+///
+/// Relays only stop for good once their root stream closes after a bounded
+/// scan, or if they have given up restarting. Fails if any relay did not stop
+/// cleanly.
+async fn drain_relays(joinset: &mut JoinSet<Result<()>>) -> Result<()> {
+    let mut failed = 0;
+    while let Some(res) = joinset.join_next().await {
+        match res {
+            Ok(Ok(())) => tracing::info!("Relay task stopped"),
+            Ok(Err(error)) => {
+                failed += 1;
+                tracing::error!(%error, "Relay task abandoned");
+            }
+            Err(error) => {
+                failed += 1;
+                tracing::error!(%error, "Relay task panicked");
+            }
+        }
+    }
+    if failed > 0 {
+        bail!("{failed} relay task(s) stopped with an error");
+    }
+    Ok(())
+}
+
+/// Propagates the current canonical root to the named bridged network once,
+/// without starting the scanner or any other relay.
+async fn propagate(
+    mut config: Config,
+    network: &str,
+    dry_run: bool,
+) -> Result<()> {
+    config
+        .bridged_networks
+        .retain(|bridged| bridged.name == network);
+    if config.bridged_networks.is_empty() {
+        bail!("No bridged network named {network}");
+    }
+    if !config.bridged_networks[0].enabled {
+        bail!("Bridged network {network} is disabled");
+    }
+
+    let canonical = config
+        .canonical_for(&config.bridged_networks[0])
+        .ok_or_else(|| eyre!("No canonical network for {network}"))?;
+    let canonical_provider = canonical.provider.http_provider();
+    let canonical_world_id = IWorldIDIdentityManagerInstance::new(
+        canonical.world_id_addr,
+        canonical_provider,
+    );
+    let root = canonical_world_id.latestRoot().call().await?._0;
+
+    let Some(Relayer::EVMRelay(relay)) =
+        init_relays(config, &RelayEvents::new(), &RelayStatus::new(), dry_run)
+            .await?
+            .pop()
+    else {
+        bail!("Manual propagation is only supported for EVM networks");
     };
 
-    tokio::select! {
-        _ = scanner_fut => {
-            tracing::error!("Scanner task failed");
+    match relay.propagate_to_bridges(root).await {
+        Ok(Propagation::Sent(tx_hash)) => {
+            println!("Propagated root {root} to {network}: {tx_hash}");
+            Ok(())
+        }
+        Ok(Propagation::Submitted) => {
+            println!(
+                "No transaction sent for root {root} to {network}, see the logs for the Safe proposal"
+            );
+            Ok(())
+        }
+        Ok(Propagation::DryRun) => {
+            println!(
+                "Dry run, no transaction sent for root {root} to {network}"
+            );
+            Ok(())
+        }
+        Ok(Propagation::Skipped) => {
+            bail!(
+                "Skipped propagating root {root} to {network}, the gas price exceeds the wallet ceiling"
+            )
         }
-        _ = joinset.join_all() => {
-            tracing::error!("Relayer task failed");
+        Err(error) => {
+            eprintln!("Failed to propagate root {root} to {network}: {error}");
+            Err(error)
         }
     }
-    Ok(())
+}
+
+/// Re-propagates the roots recorded in the dead letter file, keeping only
+/// those that fail again.
+///
+/// A propagation always carries the network's latest canonical root, so each
+/// network is propagated to once and all of its entries are resolved by a
+/// single success. Entries for networks no longer relayed to are kept.
+async fn retry_dead_letters(config: Config, dry_run: bool) -> Result<()> {
+    let dead_letters =
+        config
+            .dead_letter_path
+            .as_ref()
+            .map(DeadLetters::new)
+            .ok_or_else(|| eyre!("No dead_letter_path configured"))?;
+    let entries = dead_letters.read()?;
+    if entries.is_empty() {
+        println!("No dead letters in {}", dead_letters.path().display());
+        return Ok(());
+    }
+
+    let mut networks = entries
+        .iter()
+        .map(|entry| entry.network.as_str())
+        .collect::<Vec<_>>();
+    networks.sort_unstable();
+    networks.dedup();
+
+    let relays =
+        init_relays(config, &RelayEvents::new(), &RelayStatus::new(), dry_run)
+            .await?;
+    let mut resolved = Vec::new();
+    for network in networks {
+        let Some(relay) = relays.iter().find_map(|relay| match relay {
+            Relayer::EVMRelay(relay) if relay.name == network => Some(relay),
+            _ => None,
+        }) else {
+            eprintln!(
+                "Keeping dead letters for {network}, which is not relayed to"
+            );
+            continue;
+        };
+
+        match relay.propagate_now().await {
+            Ok((_, Propagation::Skipped)) => {
+                eprintln!(
+                    "Skipped propagating to {network}, the gas price exceeds the wallet ceiling"
+                );
+            }
+            Ok((root, propagation)) => {
+                println!(
+                    "Propagated root {root} to {network}: {:?}",
+                    propagation.tx_hash()
+                );
+                resolved.push(network.to_owned());
+            }
+            Err(error) => {
+                eprintln!("Failed to propagate to {network}: {error}");
+            }
+        }
+    }
+
+    // Dry runs send nothing, so nothing is resolved
+    if dry_run {
+        return Ok(());
+    }
+
+    let remaining = entries
+        .into_iter()
+        .filter(|entry| !resolved.contains(&entry.network))
+        .collect::<Vec<_>>();
+    dead_letters.write(&remaining)?;
+    println!("{} dead letters remaining", remaining.len());
+
+    if remaining.is_empty() {
+        Ok(())
+    } else {
+        bail!("{} dead letters could not be propagated", remaining.len())
+    }
 }
 
 /// Initializes the relayers for the bridged networks.
 ///
 /// Additionally initializes the signers from the global wallet configuration if present,
 /// otherwise from the bridged network configuration.
-fn init_relays(cfg: Config) -> Result<Vec<Relayer>> {
-    // A global signer is required when using an [`AlloySigner`]
-    // in order to keep the transaction nonce in sync.
+async fn init_relays(
+    cfg: Config,
+    events: &RelayEvents,
+    status: &RelayStatus,
+    dry_run: bool,
+) -> Result<Vec<Relayer>> {
+    let health = Arc::new(NetworkHealth::new(
+        cfg.alerting
+            .as_ref()
+            .map(|alerting| alerting.widespread_outage_threshold),
+    ));
+
+    for bridged in &cfg.bridged_networks {
+        if !bridged.enabled {
+            tracing::info!(network = %bridged.name, "Skipping disabled network");
+            continue;
+        }
+        bridged.provider.verify_chain_id(&bridged.name).await?;
+        if bridged.verify_state_bridge {
+            // State bridges are deployed on the canonical chain
+            let canonical = cfg.canonical_for(bridged).ok_or_else(|| {
+                eyre!("No canonical network for {}", bridged.name)
+            })?;
+            for &state_bridge in &bridged.state_bridge_addrs {
+                canonical
+                    .provider
+                    .verify_deployed(&bridged.name, state_bridge)
+                    .await?;
+            }
+        }
+    }
+
+    // Signer providers are shared between relays using the same signer on the
+    // same canonical chain when using an [`AlloySigner`] in order to keep the
+    // transaction nonce in sync.
     let mut alloy_signer_providers =
-        HashMap::<String, Arc<AlloySignerProvider>>::new();
+        HashMap::<(Address, Vec<Url>), Arc<AlloySignerProvider>>::new();
+    let propagation_permits = cfg
+        .max_concurrent_propagations
+        .map(|permits| Arc::new(Semaphore::new(permits)));
+    let dead_letters = cfg.dead_letter_path.as_ref().map(DeadLetters::new);
 
     cfg.bridged_networks
         .iter()
+        .filter(|bridged| bridged.enabled)
         .map(|bridged| {
+            let canonical_config =
+                cfg.canonical_for(bridged).ok_or_else(|| {
+                    eyre!("No canonical network for {}", bridged.name)
+                })?;
+            let canonical = CanonicalWorldId {
+                world_id_address: canonical_config.world_id_addr,
+                provider: canonical_config.provider.clone(),
+                initial_backoff: Duration::from_millis(
+                    canonical_config.provider.initial_backoff,
+                ),
+                max_attempts: canonical_config.provider.max_rate_limit_retries
+                    as usize
+                    + 1,
+                gated: canonical_config.gates_roots(),
+            };
             let wallet_config = bridged
                 .wallet
                 .clone()
-                .or(cfg.canonical_network.wallet.clone())
+                .or(canonical_config.wallet.clone())
                 .ok_or_else(|| eyre!("No wallet configuration found"))?;
 
             match bridged.ty {
-                NetworkType::Evm => match wallet_config {
-                    WalletConfig::Mnemonic { mnemonic } => {
-                        let provider = match alloy_signer_providers
-                            .get(&mnemonic)
-                        {
-                            Some(provider) => provider.clone(),
-                            None => {
-                                let signer =
-                                    MnemonicBuilder::<English>::default()
-                                        .phrase(&mnemonic)
-                                        .index(0)?
-                                        .build()?;
-                                let wallet = EthereumWallet::new(signer);
-                                let provider = Arc::new(
-                                    cfg.canonical_network
-                                        .provider
-                                        .signer(wallet.clone()),
-                                );
-                                alloy_signer_providers
-                                    .insert(mnemonic.clone(), provider.clone());
-                                provider
-                            }
-                        };
-
-                        let alloy_signer = AlloySigner::new(
-                            bridged.state_bridge_addr,
-                            provider,
-                        );
-
-                        Ok(Relayer::EVMRelay(EVMRelay::new(
-                            Signer::AlloySigner(alloy_signer),
-                            bridged.world_id_addr,
-                            bridged.provider.rpc_endpoint.clone(),
-                        )))
-                    }
-                    WalletConfig::TxSitter { url, gas_limit } => {
-                        let signer = TxSitterSigner::new(
-                            url.as_str(),
-                            bridged.state_bridge_addr,
-                            gas_limit,
-                        );
-
-                        Ok(Relayer::EVMRelay(EVMRelay::new(
-                            Signer::TxSitterSigner(signer),
-                            bridged.world_id_addr,
-                            bridged.provider.rpc_endpoint.clone(),
-                        )))
+                NetworkType::Evm => {
+                    tracing::info!(
+                        network = %bridged.name,
+                        canonical = %canonical_config.name,
+                        bridge = ?bridged.bridge,
+                        "Initializing relay"
+                    );
+                    let mut signers = Vec::new();
+                    for &state_bridge in &bridged.state_bridge_addrs {
+                        let signer = init_signer(
+                            bridged,
+                            &canonical_config.provider,
+                            state_bridge,
+                            &wallet_config,
+                            &mut alloy_signer_providers,
+                            dry_run,
+                        )?;
+                        signers.push((state_bridge, signer));
                     }
-                },
-                NetworkType::Svm => unimplemented!(),
-                NetworkType::Scroll => unimplemented!(),
+
+                    Ok(Relayer::EVMRelay(EVMRelay::new(
+                        bridged,
+                        signers,
+                        canonical.clone(),
+                        events.clone(),
+                        health.clone(),
+                        status.clone(),
+                    )
+                    .with_propagation_permits(propagation_permits.clone())
+                    .with_dead_letters(dead_letters.clone())))
+                }
+                ref ty @ (NetworkType::Svm | NetworkType::Scroll) => bail!(
+                    "Bridged network {}: network type {ty:?} is not supported yet",
+                    bridged.name
+                ),
             }
         })
         .collect()
 }
+
+/// Initializes the signer propagating roots through one of the bridged
+/// network's state bridges.
+///
+/// State bridges live on the canonical chain, so propagations are signed and
+/// sent through the canonical provider.
+fn init_signer(
+    bridged: &BridgedNetworkConfig,
+    canonical: &ProviderConfig,
+    state_bridge: Address,
+    wallet_config: &WalletConfig,
+    alloy_signer_providers: &mut HashMap<
+        (Address, Vec<Url>),
+        Arc<AlloySignerProvider>,
+    >,
+    dry_run: bool,
+) -> Result<Signer> {
+    let signer = match wallet_config {
+        WalletConfig::Mnemonic {
+            mnemonic, index, ..
+        } => mnemonic_signer(mnemonic, *index)?,
+        WalletConfig::PrivateKey { private_key, .. } => {
+            private_key_signer(&bridged.name, private_key)?
+        }
+        WalletConfig::Keystore {
+            path, password_env, ..
+        } => keystore_signer(&bridged.name, path, password_env)?,
+        WalletConfig::TxSitter {
+            url,
+            username,
+            password,
+            auth_token,
+            gas_limit,
+            tx_timeout_secs,
+            poll_interval_secs,
+            max_poll_interval_secs,
+            pending_log_every,
+            verify_receipt,
+            priority,
+            ..
+        } => {
+            let url = tx_sitter_url(url, username.as_deref(), password)?;
+            let auth_token =
+                (!auth_token.is_empty()).then_some(auth_token.as_str());
+            let tx_sitter = TxSitterClient::new(url.as_str(), auth_token)
+                .map_err(|e| eyre!("Network {}: {e}", bridged.name))?;
+            let signer = TxSitterSigner::new(
+                bridged,
+                state_bridge,
+                tx_sitter,
+                *gas_limit,
+                Duration::from_secs(*tx_timeout_secs),
+                Duration::from_secs(*poll_interval_secs),
+                dry_run,
+            )
+            .with_receipt_provider(
+                verify_receipt.then(|| canonical.http_provider()),
+            )
+            .with_max_poll_interval(Duration::from_secs(
+                *max_poll_interval_secs,
+            ))
+            .with_priority(*priority)
+            .with_pending_log_every(*pending_log_every);
+
+            return Ok(Signer::TxSitterSigner(signer));
+        }
+        WalletConfig::Safe {
+            safe_address,
+            service_url,
+            proposer_key,
+            ..
+        } => {
+            return Ok(Signer::SafeSigner(SafeSigner {
+                network: bridged.name.clone(),
+                state_bridge_address: state_bridge,
+                safe_address: *safe_address,
+                service: SafeServiceClient::new(service_url.clone()),
+                proposer: private_key_signer(&bridged.name, proposer_key)?,
+                provider: canonical.provider(),
+                dry_run,
+            }));
+        }
+    };
+
+    let provider = alloy_signer_providers
+        .entry((signer.address(), canonical.rpc_endpoints.clone()))
+        .or_insert_with(|| {
+            let wallet = EthereumWallet::new(signer);
+            Arc::new(canonical.signer(wallet))
+        })
+        .clone();
+
+    Ok(Signer::AlloySigner(
+        AlloySigner::new(
+            bridged,
+            state_bridge,
+            provider,
+            wallet_config.tx_overrides(),
+            wallet_config.confirmations(),
+            dry_run,
+        )
+        .with_max_gas_price(wallet_config.max_gas_price_wei()),
+    ))
+}
+
+/// Derives the signer at the given index from a BIP-39 mnemonic.
+fn mnemonic_signer(mnemonic: &str, index: u32) -> Result<PrivateKeySigner> {
+    Ok(MnemonicBuilder::<English>::default()
+        .phrase(mnemonic)
+        .index(index)?
+        .build()?)
+}
+
+/// Parses a hex encoded private key, with or without the `0x` prefix.
+fn private_key_signer(
+    network: &str,
+    private_key: &str,
+) -> Result<PrivateKeySigner> {
+    let private_key = private_key.trim();
+    let private_key = private_key.strip_prefix("0x").unwrap_or(private_key);
+
+    let bytes = hex::decode(private_key).map_err(|e| {
+        eyre!("Private key for network {network} is not valid hex: {e}")
+    })?;
+    if bytes.len() != 32 {
+        return Err(eyre!(
+            "Private key for network {network} must be 32 bytes, got {}",
+            bytes.len()
+        ));
+    }
+
+    PrivateKeySigner::from_slice(&bytes)
+        .map_err(|e| eyre!("Private key for network {network} is invalid: {e}"))
+}
+
+/// Decrypts a JSON V3 keystore with the password read from `password_env`.
+fn keystore_signer(
+    network: &str,
+    path: &Path,
+    password_env: &str,
+) -> Result<PrivateKeySigner> {
+    let password = std::env::var(password_env).map_err(|_| {
+        eyre!(
+            "Keystore password variable {password_env} for network {network} is not set"
+        )
+    })?;
+
+    if !path.exists() {
+        return Err(eyre!(
+            "Keystore file {} for network {network} does not exist",
+            path.display()
+        ));
+    }
+
+    PrivateKeySigner::decrypt_keystore(path, password).map_err(|e| {
+        eyre!(
+            "Failed to decrypt keystore {} for network {network}: {e}",
+            path.display()
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::sync::Mutex;
+
+    use alloy::primitives::U256;
+    use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+
+    use super::*;
+
+    #[test]
+    fn prometheus_metrics_are_tagged_with_region() {
+        let prometheus = PrometheusConfig {
+            address: "127.0.0.1:0".parse().unwrap(),
+        };
+        let recorder =
+            prometheus_builder(&prometheus, Some("eu-west-1")).build_recorder();
+
+        metrics::with_local_recorder(&recorder, || {
+            metrics::counter!("roots_propagated").increment(1);
+        });
+
+        let rendered = recorder.handle().render();
+        assert!(
+            rendered.contains(r#"roots_propagated{region="eu-west-1"} 1"#),
+            "{rendered}"
+        );
+    }
+
+    #[derive(Clone, Default)]
+    struct Logs(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Logs {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn logs_are_tagged_with_region() {
+        let logs = Logs::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .json()
+            .with_writer(move || writer.clone())
+            .finish();
+
+        tracing::subscriber::with_default(subscriber, || {
+            let _span = root_span(Some("eu-west-1")).entered();
+            info!("Relay started");
+        });
+
+        let logs = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+        assert!(logs.contains(r#""region":"eu-west-1""#), "{logs}");
+    }
+
+    fn tree_changed(root: u64) -> TreeChangedLog {
+        TreeChangedLog {
+            event: TreeChanged {
+                preRoot: U256::from(1),
+                kind: 0,
+                postRoot: U256::from(root),
+            },
+            transaction_hash: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn roots_failing_verification_are_held() {
+        let recorder = DebuggingRecorder::new();
+        let snapshotter = recorder.snapshotter();
+        // The test runtime is single threaded, so the whole test records here
+        let _guard = metrics::set_default_local_recorder(&recorder);
+
+        let (tx, mut rx) = tokio::sync::broadcast::channel(8);
+        let events = RelayEvents::new();
+        // No endpoint answers, so no root is ever verified
+        let unreachable = ["http://127.0.0.1:1".parse().unwrap()];
+        let verifier = RootVerifier::new(Address::ZERO, &unreachable, None);
+
+        forward_root(&tree_changed(2), &tx, &events, None, Some(&verifier), 1)
+            .await;
+        assert!(rx.try_recv().is_err(), "held root was sent to the relays");
+
+        forward_root(&tree_changed(3), &tx, &events, None, None, 1).await;
+        assert_eq!(rx.try_recv().unwrap().post_root, U256::from(3));
+
+        let failed = snapshotter
+            .snapshot()
+            .into_vec()
+            .into_iter()
+            .find(|(key, ..)| key.key().name() == "root_verification_failed")
+            .map(|(.., value)| value);
+        assert_eq!(failed, Some(DebugValue::Counter(1)));
+    }
+}
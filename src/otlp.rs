@@ -0,0 +1,70 @@
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::trace::{self, TracerProvider};
+use opentelemetry_sdk::{runtime, Resource};
+use tracing::Subscriber;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+use crate::config::{OtlpProtocol, TelemetryConfig};
+
+/// Flushes the spans still queued for export when dropped
+pub struct OtlpShutdownHandle(TracerProvider);
+
+impl Drop for OtlpShutdownHandle {
+    fn drop(&mut self) {
+        if let Err(error) = self.0.shutdown() {
+            eprintln!("Failed to shut down the OTLP exporter: {error}");
+        }
+    }
+}
+
+/// Builds a layer exporting spans over OTLP to the telemetry
+/// `traces_endpoint`, or to the collector's default address if unset.
+pub fn layer<S>(
+    telemetry: &TelemetryConfig,
+    region: Option<&str>,
+) -> eyre::Result<(impl Layer<S>, OtlpShutdownHandle)>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    let mut attributes = vec![KeyValue::new(
+        "service.name",
+        telemetry.service_name.clone(),
+    )];
+    if let Some(region) = region {
+        attributes.push(KeyValue::new("cloud.region", region.to_owned()));
+    }
+    let pipeline = opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_trace_config(
+            trace::Config::default().with_resource(Resource::new(attributes)),
+        );
+
+    let provider = match telemetry.otlp_protocol {
+        OtlpProtocol::Grpc => {
+            let mut exporter = opentelemetry_otlp::new_exporter().tonic();
+            if let Some(endpoint) = &telemetry.traces_endpoint {
+                exporter = exporter.with_endpoint(endpoint);
+            }
+            pipeline
+                .with_exporter(exporter)
+                .install_batch(runtime::Tokio)?
+        }
+        OtlpProtocol::Http => {
+            let mut exporter = opentelemetry_otlp::new_exporter().http();
+            if let Some(endpoint) = &telemetry.traces_endpoint {
+                exporter = exporter.with_endpoint(endpoint);
+            }
+            pipeline
+                .with_exporter(exporter)
+                .install_batch(runtime::Tokio)?
+        }
+    };
+
+    let tracer = provider.tracer(env!("CARGO_PKG_NAME"));
+    let layer = tracing_opentelemetry::layer().with_tracer(tracer);
+
+    Ok((layer, OtlpShutdownHandle(provider)))
+}
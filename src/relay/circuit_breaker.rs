@@ -0,0 +1,86 @@
+use std::time::{Duration, Instant};
+
+use telemetry_batteries::reexports::metrics::{self, Label};
+
+use crate::config::CircuitBreakerConfig;
+
+/// Stops a relay from attempting propagations after too many consecutive
+/// failures.
+///
+/// Once open the circuit rejects propagations until the cooldown has elapsed,
+/// then half-opens to let a single propagation through. A failure while
+/// half-open reopens the circuit, a success closes it.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    network: String,
+    labels: Vec<Label>,
+    config: Option<CircuitBreakerConfig>,
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    pub fn new(
+        network: String,
+        labels: Vec<Label>,
+        config: Option<CircuitBreakerConfig>,
+    ) -> Self {
+        Self {
+            network,
+            labels,
+            config,
+            consecutive_failures: 0,
+            open_until: None,
+        }
+    }
+
+    /// Whether propagations are currently being rejected.
+    pub fn is_open(&self) -> bool {
+        self.open_until
+            .is_some_and(|open_until| Instant::now() < open_until)
+    }
+
+    /// Whether a propagation may be attempted now.
+    pub fn allows(&mut self) -> bool {
+        match self.open_until {
+            Some(open_until) if Instant::now() < open_until => false,
+            Some(_) => {
+                tracing::info!(
+                    network = %self.network,
+                    "Circuit half-open, retrying a single propagation"
+                );
+                self.open_until = None;
+                true
+            }
+            None => true,
+        }
+    }
+
+    pub fn record_success(&mut self) {
+        if self.consecutive_failures > 0 {
+            metrics::gauge!("relay_circuit_open", self.labels.clone()).set(0.0);
+        }
+        self.consecutive_failures = 0;
+    }
+
+    pub fn record_failure(&mut self) {
+        self.consecutive_failures += 1;
+
+        let Some(config) = &self.config else {
+            return;
+        };
+        if self.consecutive_failures < config.failure_threshold {
+            return;
+        }
+
+        let cooldown = Duration::from_secs(config.cooldown_secs);
+        self.open_until = Some(Instant::now() + cooldown);
+        metrics::gauge!("relay_circuit_open", self.labels.clone()).set(1.0);
+        tracing::warn!(
+            network = %self.network,
+            consecutive_failures = self.consecutive_failures,
+            ?cooldown,
+            "Circuit open, pausing propagation"
+        );
+    }
+}
@@ -1,23 +1,63 @@
+pub mod circuit_breaker;
 pub mod signer;
+pub mod supervisor;
 
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use alloy::primitives::Address;
-use alloy::providers::ProviderBuilder;
-use eyre::Result;
+use alloy::providers::{Provider, RootProvider};
+use alloy::rpc::types::Filter;
+use alloy::sol_types::SolEvent;
+use alloy::transports::http::{Client, Http};
+use alloy::transports::{BoxTransport, RpcError};
+use circuit_breaker::CircuitBreaker;
+use eyre::eyre::{bail, eyre, Result};
 use semaphore::Field;
-use signer::{RelaySigner, Signer};
+use signer::{Propagation, RelaySigner, Signer};
+use telemetry_batteries::reexports::metrics::{self, Label};
+use tokio::sync::broadcast::error::RecvError;
 use tokio::sync::broadcast::Receiver;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tracing::Instrument;
 use url::Url;
 
-use crate::abi::IBridgedWorldID::IBridgedWorldIDInstance;
+use crate::abi::IBridgedWorldID::{IBridgedWorldIDInstance, RootAdded};
+use crate::abi::IWorldIDIdentityManager::{
+    IWorldIDIdentityManagerInstance, TreeChanged,
+};
+use crate::config::{
+    BridgeKind, BridgedNetworkConfig, CircuitBreakerConfig, ConfirmationConfig,
+    ProviderConfig, ReconnectConfig, RestartConfig,
+};
+use crate::dead_letter::{DeadLetterEntry, DeadLetters};
+use crate::events::{RelayEvent, RelayEvents};
+use crate::health::NetworkHealth;
+use crate::status::RelayStatus;
+use crate::utils::retry_with_attempts;
 
 // Two Mainnet Blocks
 pub const ROOT_PROPAGATION_BACKOFF: u64 = 24;
 
+/// Interval between L2 log queries while waiting for `RootAdded`
+pub const ROOT_ADDED_POLL_INTERVAL: Duration = Duration::from_secs(6);
+
+/// Interval between L2 log queries while waiting for a Polygon checkpoint
+pub const CHECKPOINT_POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Delay before a skipped propagation is retried if no newer root arrives
+pub const SKIPPED_PROPAGATION_RETRY: Duration = Duration::from_secs(60);
+
+type BridgedWorldId =
+    IBridgedWorldIDInstance<BoxTransport, RootProvider<BoxTransport>>;
+
+type CanonicalWorldIdInstance =
+    IWorldIDIdentityManagerInstance<Http<Client>, RootProvider<Http<Client>>>;
+
 pub(crate) trait Relay {
     /// Subscribe to the stream of new Roots on L1.
-    async fn subscribe_roots(&self, rx: Receiver<Field>) -> Result<()>;
+    async fn subscribe_roots(&self, rx: Receiver<RootUpdate>) -> Result<()>;
 }
 
 macro_rules! relay {
@@ -26,7 +66,7 @@ macro_rules! relay {
             $($relay_type($relay_type),)+
         }
         impl Relay for Relayer {
-            async fn subscribe_roots(&self, rx: Receiver<Field>) -> Result<()> {
+            async fn subscribe_roots(&self, rx: Receiver<RootUpdate>) -> Result<()> {
                 match self {
                     $(Relayer::$relay_type(relay) => Ok(relay.subscribe_roots(rx).await?),)+
                 }
@@ -35,63 +75,1504 @@ macro_rules! relay {
     }
 }
 
+/// The kind of tree change that produced a root
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TreeChangeKind {
+    Insertion,
+    Deletion,
+    Update,
+    Unknown(u8),
+}
+
+impl TreeChangeKind {
+    /// The metric label of the kind, unknown kinds share a single label.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Insertion => "insertion",
+            Self::Deletion => "deletion",
+            Self::Update => "update",
+            Self::Unknown(_) => "unknown",
+        }
+    }
+}
+
+impl From<u8> for TreeChangeKind {
+    fn from(kind: u8) -> Self {
+        match kind {
+            0 => Self::Insertion,
+            1 => Self::Deletion,
+            2 => Self::Update,
+            kind => Self::Unknown(kind),
+        }
+    }
+}
+
+/// Marks a root as in flight while holding the relay's propagation lock,
+/// clearing it when dropped, even if the propagation future is cancelled.
+struct InFlight<'a> {
+    in_flight: &'a Mutex<Option<Field>>,
+    _propagating: tokio::sync::MutexGuard<'a, ()>,
+}
+
+impl<'a> InFlight<'a> {
+    fn new(
+        relay: &'a EVMRelay,
+        root: Field,
+        propagating: tokio::sync::MutexGuard<'a, ()>,
+    ) -> Self {
+        *relay.in_flight.lock().expect("in flight lock poisoned") = Some(root);
+        Self {
+            in_flight: &relay.in_flight,
+            _propagating: propagating,
+        }
+    }
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        *self.in_flight.lock().expect("in flight lock poisoned") = None;
+    }
+}
+
+/// A new canonical root, as broadcast to the relays
+#[derive(Clone, Copy, Debug)]
+pub struct RootUpdate {
+    pub post_root: Field,
+    /// The root that was replaced, unknown if the update was read from the
+    /// latest root rather than a `TreeChanged` event
+    pub pre_root: Option<Field>,
+    /// The kind of change, unknown if the update was read from the latest root
+    pub kind: Option<TreeChangeKind>,
+}
+
+impl RootUpdate {
+    /// An update carrying only the latest root, without its provenance.
+    pub fn latest(root: Field) -> Self {
+        Self {
+            post_root: root,
+            pre_root: None,
+            kind: None,
+        }
+    }
+}
+
+impl From<&TreeChanged> for RootUpdate {
+    fn from(event: &TreeChanged) -> Self {
+        Self {
+            post_root: event.postRoot,
+            pre_root: Some(event.preRoot),
+            kind: Some(TreeChangeKind::from(event.kind)),
+        }
+    }
+}
+
+/// The canonical World ID deployment from which roots are propagated.
+#[derive(Clone, Debug)]
+pub struct CanonicalWorldId {
+    pub world_id_address: Address,
+    pub provider: ProviderConfig,
+    /// The initial backoff between retried root reads
+    pub initial_backoff: Duration,
+    /// The number of attempts at reading the root before giving up
+    pub max_attempts: usize,
+    /// Whether the scanner gates roots before they are broadcast, which roots
+    /// read from `latestRoot` would bypass, so none are propagated
+    pub gated: bool,
+}
+
 pub struct EVMRelay {
-    pub signer: Signer,
+    pub name: String,
+    /// One signer per state bridge, roots are propagated through them in
+    /// order
+    pub signers: Vec<(Address, Signer)>,
     pub world_id_address: Address,
+    /// The primary L2 endpoint, reported in logs
     pub provider: Url,
+    /// The L2 endpoints reads fail over between
+    pub l2_provider: ProviderConfig,
+    pub canonical: CanonicalWorldId,
+    pub events: RelayEvents,
+    pub health: Arc<NetworkHealth>,
+    pub status: RelayStatus,
+    /// Labels of the relay's metrics, its name and configured tags
+    pub labels: Vec<Label>,
+    /// Backoff bounds for rebuilding the L2 provider after transport failures
+    pub reconnect: ReconnectConfig,
+    /// Backoff bounds for restarting the relay after it fails
+    pub restart: RestartConfig,
+    /// How delivery of a propagated root to the L2 is confirmed
+    pub confirmation: ConfirmationConfig,
+    /// Roots received within this interval of the last propagation are
+    /// coalesced, only the most recent one is propagated
+    pub min_propagation_interval: Option<Duration>,
+    /// When to pause propagation after consecutive failures
+    pub circuit_breaker: Option<CircuitBreakerConfig>,
+    /// The kinds of tree changes whose roots are propagated, all if unset
+    pub propagate_kinds: Option<Vec<TreeChangeKind>>,
+    /// The kind of bridge carrying roots to the network
+    pub bridge: BridgeKind,
+    /// How long to wait for a Polygon checkpoint to deliver a root
+    pub checkpoint_timeout: Duration,
+    /// Propagations taking longer are abandoned so the next root can be
+    /// attempted
+    pub propagation_timeout: Duration,
+    /// Propagate the latest canonical root on startup if the L2 differs
+    pub reconcile_on_startup: bool,
+    /// How long to wait after a propagation before the L2 root is read again
+    propagation_backoff: Duration,
+    /// How long to wait before retrying a skipped propagation
+    skipped_retry: Duration,
+    /// The root currently being propagated, if any
+    in_flight: Mutex<Option<Field>>,
+    /// Serializes streamed and manual propagations, which share signers and
+    /// their nonces
+    propagating: tokio::sync::Mutex<()>,
+    /// Roots are received but not propagated while set, kept across restarts
+    paused: AtomicBool,
+    /// Permits bounding the propagations in flight across all relays
+    propagation_permits: Option<Arc<Semaphore>>,
+    /// Where roots that failed to propagate are recorded
+    dead_letters: Option<DeadLetters>,
+}
+
+/// A held propagation permit, reported by the `propagation_permits_held`
+/// gauge until dropped
+struct PropagationPermit(OwnedSemaphorePermit);
+
+impl PropagationPermit {
+    async fn acquire(permits: &Arc<Semaphore>, network: &str) -> Result<Self> {
+        if permits.available_permits() == 0 {
+            tracing::debug!(network, "Waiting for a propagation permit");
+        }
+        let permit = permits
+            .clone()
+            .acquire_owned()
+            .await
+            .map_err(|_| eyre!("Propagation permits closed"))?;
+        metrics::gauge!("propagation_permits_held").increment(1.0);
+        Ok(Self(permit))
+    }
+}
+
+impl Drop for PropagationPermit {
+    fn drop(&mut self) {
+        metrics::gauge!("propagation_permits_held").decrement(1.0);
+    }
 }
 
 impl EVMRelay {
     pub fn new(
-        signer: Signer,
-        world_id_address: Address,
-        provider: Url,
+        bridged: &BridgedNetworkConfig,
+        signers: Vec<(Address, Signer)>,
+        canonical: CanonicalWorldId,
+        events: RelayEvents,
+        health: Arc<NetworkHealth>,
+        status: RelayStatus,
     ) -> Self {
+        // Listed on `/status` before the first root arrives
+        status.update(&bridged.name, |_| {});
         Self {
-            signer,
-            world_id_address,
-            provider,
+            name: bridged.name.clone(),
+            signers,
+            world_id_address: bridged.world_id_addr,
+            provider: bridged.provider.rpc_endpoint().clone(),
+            l2_provider: bridged.provider.clone(),
+            canonical,
+            events,
+            health,
+            status,
+            labels: bridged.metric_labels(),
+            reconnect: bridged.reconnect.clone(),
+            restart: bridged.restart.clone(),
+            confirmation: bridged.confirmation.clone(),
+            min_propagation_interval: bridged
+                .min_propagation_interval_secs
+                .map(Duration::from_secs),
+            circuit_breaker: bridged.circuit_breaker.clone(),
+            bridge: bridged.bridge,
+            checkpoint_timeout: Duration::from_secs(
+                bridged.polygon_checkpoint_timeout_secs,
+            ),
+            propagation_timeout: Duration::from_secs(
+                bridged.propagation_timeout_secs,
+            ),
+            reconcile_on_startup: bridged.reconcile_on_startup,
+            propagation_backoff: Duration::from_secs(ROOT_PROPAGATION_BACKOFF),
+            skipped_retry: SKIPPED_PROPAGATION_RETRY,
+            propagate_kinds: bridged.propagate_kinds.as_ref().map(|kinds| {
+                kinds.iter().copied().map(TreeChangeKind::from).collect()
+            }),
+            in_flight: Mutex::new(None),
+            propagating: tokio::sync::Mutex::new(()),
+            paused: AtomicBool::new(false),
+            propagation_permits: None,
+            dead_letters: None,
+        }
+    }
+
+    /// Records roots that failed to propagate.
+    pub fn with_dead_letters(
+        mut self,
+        dead_letters: Option<DeadLetters>,
+    ) -> Self {
+        self.dead_letters = dead_letters;
+        self
+    }
+
+    /// Shares the permits bounding the propagations in flight across relays.
+    pub fn with_propagation_permits(
+        mut self,
+        permits: Option<Arc<Semaphore>>,
+    ) -> Self {
+        self.propagation_permits = permits;
+        self
+    }
+
+    async fn l2_world_id(&self) -> Result<BridgedWorldId> {
+        Ok(IBridgedWorldIDInstance::new(
+            self.world_id_address,
+            self.l2_provider.read_provider().await?,
+        ))
+    }
+
+    /// Reads the latest root on the L2.
+    ///
+    /// Failed reads are retried with bounded backoff before giving up, if the
+    /// transport failed the L2 provider is rebuilt as well.
+    async fn l2_latest_root(
+        &self,
+        world_id: &mut BridgedWorldId,
+    ) -> Result<Field> {
+        let mut backoff =
+            Duration::from_millis(self.reconnect.initial_backoff_ms);
+        let max_backoff = Duration::from_secs(self.reconnect.max_backoff_secs);
+        let mut attempt = 0;
+
+        loop {
+            match world_id.latestRoot().call().await {
+                Ok(latest) => return Ok(latest._0),
+                Err(error) if attempt < self.reconnect.max_attempts => {
+                    attempt += 1;
+                    let reconnect = matches!(
+                        error,
+                        alloy::contract::Error::TransportError(
+                            RpcError::Transport(_)
+                        )
+                    );
+                    tracing::warn!(
+                        network = %self.name,
+                        provider = %self.provider,
+                        %error,
+                        attempt,
+                        ?backoff,
+                        reconnect,
+                        "Failed to read L2 root, retrying"
+                    );
+                    tokio::time::sleep(backoff).await;
+                    backoff = (backoff * 2).min(max_backoff);
+                    if reconnect {
+                        match self.l2_world_id().await {
+                            Ok(reconnected) => *world_id = reconnected,
+                            Err(error) => tracing::warn!(
+                                network = %self.name,
+                                provider = %self.provider,
+                                %error,
+                                "Failed to reconnect to the L2"
+                            ),
+                        }
+                    }
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    fn canonical_world_id(&self) -> CanonicalWorldIdInstance {
+        IWorldIDIdentityManagerInstance::new(
+            self.canonical.world_id_address,
+            self.canonical.provider.http_provider(),
+        )
+    }
+
+    /// Reads the latest root on the canonical network, retrying transient
+    /// failures with the canonical provider's backoff.
+    async fn canonical_latest_root(
+        &self,
+        canonical_world_id: &CanonicalWorldIdInstance,
+    ) -> Result<Field> {
+        let world_id = canonical_world_id.clone();
+        let root = retry_with_attempts(
+            self.canonical.initial_backoff,
+            None,
+            Some(self.canonical.max_attempts),
+            move || {
+                let world_id = world_id.clone();
+                async move { world_id.latestRoot().call().await }
+            },
+        )
+        .await?;
+        Ok(root._0)
+    }
+
+    /// Publishes the outcome of a propagation to `/status`.
+    fn record_status(&self, root: Field, result: &Result<Propagation>) {
+        self.status.update(&self.name, |status| match result {
+            // Nothing was propagated
+            Ok(Propagation::DryRun | Propagation::Skipped) => {}
+            Ok(_) => {
+                status.last_propagated_root = Some(root);
+                status.last_propagated_at = Some(
+                    SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap_or_default()
+                        .as_secs(),
+                );
+                status.last_error = None;
+            }
+            Err(e) => status.last_error = Some(e.to_string()),
+        });
+    }
+
+    /// Propagates the latest canonical root right away, outside of the root
+    /// stream, returning the root and the outcome of its propagation.
+    ///
+    /// Fails without propagating if another propagation is in flight.
+    pub async fn propagate_now(&self) -> Result<(Field, Propagation)> {
+        let root = self
+            .canonical_latest_root(&self.canonical_world_id())
+            .await?;
+
+        let Ok(propagating) = self.propagating.try_lock() else {
+            match *self.in_flight.lock().expect("in flight lock poisoned") {
+                Some(in_flight) => bail!(
+                    "Propagation of root {in_flight} to {} is already in flight",
+                    self.name
+                ),
+                None => bail!(
+                    "A propagation to {} is already in flight",
+                    self.name
+                ),
+            }
+        };
+        let in_flight = InFlight::new(self, root, propagating);
+
+        tracing::info!(network = %self.name, %root, "Manually propagating root");
+        self.events.emit(RelayEvent::PropagationStarted {
+            network: self.name.clone(),
+            root,
+        });
+        let result = tokio::time::timeout(
+            self.propagation_timeout,
+            self.propagate_to_bridges(root),
+        )
+        .await
+        .unwrap_or_else(|_| {
+            Err(eyre!(
+                "Root propagation timed out after {:?}",
+                self.propagation_timeout
+            ))
+        });
+        drop(in_flight);
+        self.record_status(root, &result);
+
+        match &result {
+            Ok(Propagation::DryRun) => {
+                tracing::info!(network = %self.name, %root, "Dry run, root not propagated manually");
+            }
+            Ok(Propagation::Skipped) => {
+                tracing::warn!(network = %self.name, %root, "Manual propagation skipped");
+            }
+            Ok(propagation) => {
+                tracing::info!(network = %self.name, %root, tx_hash = ?propagation.tx_hash(), "Root propagated manually");
+                self.events.emit(RelayEvent::PropagationConfirmed {
+                    network: self.name.clone(),
+                    root,
+                });
+            }
+            Err(e) => {
+                tracing::error!(network = %self.name, %root, error = %e, "Failed to propagate root manually");
+                self.events.emit(RelayEvent::PropagationFailed {
+                    network: self.name.clone(),
+                    root,
+                    error: e.to_string(),
+                });
+            }
+        }
+
+        result.map(|propagation| (root, propagation))
+    }
+
+    /// Propagates the root through every state bridge in order, returning the
+    /// outcome through the first bridge a transaction was sent through, or
+    /// through the first bridge if none was.
+    ///
+    /// The propagation only succeeds if it succeeded through every bridge. A
+    /// failed bridge doesn't stop the remaining ones, and once any of them
+    /// delivered the root the L2 is in sync, so it isn't retried.
+    ///
+    /// A propagation permit is held throughout if concurrent propagations are
+    /// bounded, time spent waiting for one counts towards the propagation
+    /// timeout.
+    pub async fn propagate_to_bridges(
+        &self,
+        root: Field,
+    ) -> Result<Propagation> {
+        let _permit = match &self.propagation_permits {
+            Some(permits) => {
+                Some(PropagationPermit::acquire(permits, &self.name).await?)
+            }
+            None => None,
+        };
+
+        if let [(_, signer)] = self.signers.as_slice() {
+            return signer.propagate_root(root).await;
+        }
+
+        let mut outcome: Option<Propagation> = None;
+        let mut failures = Vec::new();
+        for (state_bridge, signer) in &self.signers {
+            match signer.propagate_root(root).await {
+                Ok(propagation) => {
+                    tracing::info!(network = %self.name, %root, %state_bridge, ?propagation, "Root propagated through state bridge");
+                    if outcome.is_none_or(|outcome| {
+                        outcome.tx_hash().is_none()
+                            && propagation.tx_hash().is_some()
+                    }) {
+                        outcome = Some(propagation);
+                    }
+                }
+                Err(error) => {
+                    tracing::error!(network = %self.name, %root, %state_bridge, %error, "Failed to propagate root through state bridge");
+                    failures.push(format!("{state_bridge}: {error}"));
+                }
+            }
+        }
+
+        if !failures.is_empty() {
+            bail!(
+                "Propagation failed through {} of {} state bridges: {}",
+                failures.len(),
+                self.signers.len(),
+                failures.join("; ")
+            );
+        }
+        outcome.ok_or_else(|| {
+            eyre!("No state bridges configured for {}", self.name)
+        })
+    }
+
+    /// Pauses or resumes propagation of streamed roots.
+    ///
+    /// A paused relay keeps consuming the root stream so it doesn't lag
+    /// behind, but skips every root. Roots received while paused are not
+    /// propagated on resume, and manual propagation is unaffected.
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::Relaxed);
+        self.status
+            .update(&self.name, |status| status.paused = paused);
+        if paused {
+            tracing::info!(network = %self.name, "Relay paused");
+        } else {
+            tracing::info!(network = %self.name, "Relay resumed");
+        }
+    }
+
+    /// Whether roots of the update's kind are propagated to this network.
+    ///
+    /// Updates of unknown kind are always propagated.
+    fn propagates(&self, update: &RootUpdate) -> bool {
+        match (&self.propagate_kinds, update.kind) {
+            (Some(kinds), Some(kind)) => kinds.contains(&kind),
+            _ => true,
+        }
+    }
+
+    /// Receives the next root to propagate, or `None` once the root stream
+    /// has closed.
+    ///
+    /// If the relay lagged behind the stream the latest canonical root is read
+    /// instead of the dropped ones, unless the canonical roots are gated, in
+    /// which case the relay continues from the oldest root still buffered.
+    async fn recv_root(
+        &self,
+        rx: &mut Receiver<RootUpdate>,
+        canonical_world_id: &CanonicalWorldIdInstance,
+    ) -> Result<Option<RootUpdate>> {
+        loop {
+            let update = match rx.recv().await {
+                Ok(update) => update,
+                Err(RecvError::Lagged(skipped)) => {
+                    // Intermediate roots were dropped, only the latest one matters
+                    metrics::counter!(
+                        "roots_dropped_total",
+                        self.labels.clone()
+                    )
+                    .increment(skipped);
+                    if self.canonical.gated {
+                        tracing::warn!(
+                            network = %self.name,
+                            skipped,
+                            metric = "roots_dropped_total",
+                            "Relay lagged behind the root stream, continuing from the oldest buffered root"
+                        );
+                        continue;
+                    }
+                    tracing::warn!(
+                        network = %self.name,
+                        skipped,
+                        metric = "roots_dropped_total",
+                        "Relay lagged behind the root stream, propagating the latest canonical root"
+                    );
+                    RootUpdate::latest(
+                        self.canonical_latest_root(canonical_world_id).await?,
+                    )
+                }
+                Err(RecvError::Closed) => {
+                    tracing::info!(network = %self.name, "Root stream closed");
+                    return Ok(None);
+                }
+            };
+
+            if self.propagates(&update) {
+                return Ok(Some(update));
+            }
+            tracing::debug!(network = %self.name, root = %update.post_root, kind = ?update.kind, "Ignoring root of unwanted kind");
+        }
+    }
+
+    /// Checks that the propagated root has reached the L2, warning if the
+    /// L1 transaction succeeded but the bridge message was not delivered.
+    async fn verify_delivery(
+        &self,
+        world_id: &mut BridgedWorldId,
+        root: Field,
+    ) {
+        match self.l2_latest_root(world_id).await {
+            Ok(latest) if latest == root => {
+                tracing::debug!(network = %self.name, %root, "Root delivered to L2");
+            }
+            Ok(latest) => {
+                metrics::counter!(
+                    "root_delivery_unconfirmed",
+                    self.labels.clone()
+                )
+                .increment(1);
+                tracing::warn!(
+                    network = %self.name,
+                    %root,
+                    l2_root = %latest,
+                    provider = %self.provider,
+                    "Propagated root has not been delivered to L2"
+                );
+            }
+            Err(error) => {
+                tracing::warn!(
+                    network = %self.name,
+                    %root,
+                    %error,
+                    "Failed to read L2 root to confirm delivery"
+                );
+            }
         }
     }
 }
 
 impl Relay for EVMRelay {
-    async fn subscribe_roots(&self, mut rx: Receiver<Field>) -> Result<()> {
-        let l2_provider = ProviderBuilder::new().on_http(self.provider.clone());
-        let world_id_instance = Arc::new(IBridgedWorldIDInstance::new(
-            self.world_id_address,
-            l2_provider,
-        ));
+    async fn subscribe_roots(
+        &self,
+        mut rx: Receiver<RootUpdate>,
+    ) -> Result<()> {
+        let mut world_id = self.l2_world_id().await?;
+        let chain_id = world_id.provider().get_chain_id().await?.to_string();
+
+        let canonical_world_id = self.canonical_world_id();
+
+        let mut last_propagation: Option<Instant> = None;
+        // Polygon roots stay undelivered until the next checkpoint
+        let mut awaiting_checkpoint: Option<Field> = None;
+        // The L2 keeps reporting the previous root until the bridge delivers
+        // the last one propagated, so it is not sent again in the meantime
+        let mut pending: Option<Field> = None;
+        let mut circuit = CircuitBreaker::new(
+            self.name.clone(),
+            self.labels.clone(),
+            self.circuit_breaker.clone(),
+        );
+
+        // A skipped root is retried unless a newer one arrives first, as a
+        // quiet chain may not produce one for a long time
+        let mut skipped: Option<(RootUpdate, tokio::time::Instant)> = None;
+
+        // The L2 may have fallen behind while the relay was down, the latest
+        // canonical root is handled like a new one so the gap is closed
+        // without waiting for the next tree change
+        let mut startup_root = match (
+            self.reconcile_on_startup,
+            self.canonical.gated,
+        ) {
+            (true, false) => {
+                let root =
+                    self.canonical_latest_root(&canonical_world_id).await?;
+                tracing::info!(network = %self.name, %root, "Reconciling the latest canonical root on startup");
+                Some(RootUpdate::latest(root))
+            }
+            (true, true) => {
+                tracing::info!(network = %self.name, "Canonical roots are gated, not reconciling on startup");
+                None
+            }
+            (false, _) => None,
+        };
 
         loop {
-            let field = rx.recv().await?;
-            let world_id = world_id_instance.clone();
-            let latest = world_id.latestRoot().call().await?._0;
+            let next = if let Some(update) = startup_root.take() {
+                Some(update)
+            } else if let Some((update, retry_at)) = skipped.take() {
+                tokio::select! {
+                    _ = tokio::time::sleep_until(retry_at) => {
+                        tracing::info!(network = %self.name, root = %update.post_root, "Retrying skipped propagation");
+                        Some(update)
+                    }
+                    root = self.recv_root(&mut rx, &canonical_world_id) => root?,
+                }
+            } else {
+                self.recv_root(&mut rx, &canonical_world_id).await?
+            };
+            let Some(mut update) = next else {
+                return Ok(());
+            };
+            let mut received_at = Instant::now();
+
+            // Hold the latest root until the interval has elapsed, newer roots
+            // supersede the pending one
+            if let (Some(interval), Some(last)) =
+                (self.min_propagation_interval, last_propagation)
+            {
+                let ready_at = tokio::time::Instant::from_std(last + interval);
+                loop {
+                    tokio::select! {
+                        _ = tokio::time::sleep_until(ready_at) => break,
+                        root = self.recv_root(&mut rx, &canonical_world_id) => {
+                            let Some(root) = root? else {
+                                // The stream closed, the held root is the
+                                // last one and is still propagated
+                                tokio::time::sleep_until(ready_at).await;
+                                break;
+                            };
+                            tracing::debug!(network = %self.name, superseded = %update.post_root, root = %root.post_root, "Coalescing pending root");
+                            update = root;
+                            received_at = Instant::now();
+                        }
+                    }
+                }
+            }
+
+            let field = update.post_root;
+
+            if self.paused.load(Ordering::Relaxed) {
+                self.status.update(&self.name, |status| {
+                    status.canonical_root = Some(field);
+                });
+                tracing::debug!(network = %self.name, root = %field, "Relay paused, skipping propagation");
+                continue;
+            }
+
+            // A manual propagation may be sending the same root
+            if *self.in_flight.lock().expect("in flight lock poisoned")
+                == Some(field)
+            {
+                tracing::debug!(network = %self.name, root = %field, "Root propagation already in flight, skipping");
+                continue;
+            }
+
+            if awaiting_checkpoint == Some(field) {
+                tracing::debug!(network = %self.name, root = %field, "Root awaiting Polygon checkpoint, skipping");
+                continue;
+            }
+
+            if pending == Some(field) {
+                tracing::debug!(network = %self.name, root = %field, "Root already propagated, awaiting delivery, skipping");
+                continue;
+            }
+
+            let latest = self.l2_latest_root(&mut world_id).await?;
+            self.status.update(&self.name, |status| {
+                status.canonical_root = Some(field);
+                status.l2_root = Some(latest);
+                status.in_sync = latest == field;
+            });
 
             if latest != field {
-                match self.signer.propagate_root().await {
+                if !circuit.allows() {
+                    tracing::debug!(network = %self.name, root = %field, "Circuit open, skipping propagation");
+                    continue;
+                }
+                last_propagation = Some(Instant::now());
+                // Bridge delivery is searched for from before the L1 transaction
+                let l2_block = match (&self.confirmation, self.bridge) {
+                    (ConfirmationConfig::RootAdded { .. }, _)
+                    | (_, BridgeKind::Polygon) => {
+                        Some(world_id.provider().get_block_number().await?)
+                    }
+                    (ConfirmationConfig::LatestRoot, BridgeKind::OpStack) => {
+                        None
+                    }
+                };
+                // Waits for a manual propagation of another root to finish
+                let in_flight =
+                    InFlight::new(self, field, self.propagating.lock().await);
+                self.events.emit(RelayEvent::PropagationStarted {
+                    network: self.name.clone(),
+                    root: field,
+                });
+                let result = tokio::time::timeout(
+                    self.propagation_timeout,
+                    self.propagate_to_bridges(field),
+                )
+                .await
+                .unwrap_or_else(|_| {
+                    Err(eyre!(
+                        "Root propagation timed out after {:?}",
+                        self.propagation_timeout
+                    ))
+                });
+                let sent = matches!(result, Ok(Propagation::Sent(_)));
+                let dry_run = matches!(result, Ok(Propagation::DryRun));
+                let was_skipped = matches!(result, Ok(Propagation::Skipped));
+                let mut labels = self.labels.clone();
+                labels.push(Label::new("chain_id", chain_id.clone()));
+                labels.push(Label::new(
+                    "status",
+                    match &result {
+                        Ok(Propagation::DryRun) => "dry_run",
+                        Ok(Propagation::Skipped) => "skipped",
+                        Ok(_) => "success",
+                        Err(_) => "failure",
+                    },
+                ));
+                metrics::histogram!("root_propagation_seconds", labels)
+                    .record(received_at.elapsed().as_secs_f64());
+                match &result {
+                    Ok(Propagation::DryRun) => {
+                        tracing::info!(network = %self.name, root = %field, previous_root=%latest, kind = ?update.kind, provider = %self.provider, "Dry run, root not propagated");
+                        metrics::counter!("roots_dry_run", self.labels.clone())
+                            .increment(1);
+                    }
+                    // Neither a success nor a failure, the root is retried
+                    Ok(Propagation::Skipped) => {
+                        tracing::warn!(network = %self.name, root = %field, previous_root=%latest, kind = ?update.kind, retry_in = ?self.skipped_retry, "Root propagation skipped");
+                        metrics::counter!(
+                            "root_propagation_skipped",
+                            self.labels.clone()
+                        )
+                        .increment(1);
+                        skipped = Some((
+                            update,
+                            tokio::time::Instant::now() + self.skipped_retry,
+                        ));
+                    }
                     Ok(_) => {
-                        tracing::info!(root = %field, previous_root=%latest, provider = %self.provider, "Root propagated successfully");
+                        // The full root does not fit a gauge, it is carried by this log line
+                        tracing::info!(network = %self.name, root = %field, previous_root=%latest, kind = ?update.kind, provider = %self.provider, "Root propagated successfully");
+                        self.health.record_success(&self.name);
+                        circuit.record_success();
+                        if sent {
+                            let now = SystemTime::now()
+                                .duration_since(UNIX_EPOCH)
+                                .unwrap_or_default();
+                            metrics::gauge!(
+                                "last_propagated_root_timestamp",
+                                self.labels.clone()
+                            )
+                            .set(now.as_secs_f64());
+                        }
+                        metrics::counter!(
+                            "roots_propagated",
+                            self.labels.clone()
+                        )
+                        .increment(1);
+                        self.events.emit(RelayEvent::PropagationConfirmed {
+                            network: self.name.clone(),
+                            root: field,
+                        });
                     }
                     Err(e) => {
-                        tracing::error!(error = %e, root = %field, previous_root=%latest, provider = %self.provider, "Failed to propagate root");
+                        tracing::error!(error = %e, root = %field, previous_root=%latest, kind = ?update.kind, provider = %self.provider, "Failed to propagate root");
+                        self.health.record_failure(&self.name);
+                        circuit.record_failure();
+                        metrics::counter!(
+                            "root_propagation_errors",
+                            self.labels.clone()
+                        )
+                        .increment(1);
+                        if let Some(dead_letters) = &self.dead_letters {
+                            dead_letters.append(&DeadLetterEntry::new(
+                                &self.name, field, e,
+                            ));
+                        }
+                        self.events.emit(RelayEvent::PropagationFailed {
+                            network: self.name.clone(),
+                            root: field,
+                            error: e.to_string(),
+                        });
+                    }
+                }
+                drop(in_flight);
+                if result.is_ok() && !dry_run && !was_skipped {
+                    pending = Some(field);
+                }
+                self.record_status(field, &result);
+                self.status.update(&self.name, |status| {
+                    status.circuit_open = circuit.is_open();
+                });
+
+                match (&self.confirmation, l2_block) {
+                    // Nothing was sent, so there is no delivery to wait for
+                    _ if dry_run || was_skipped => {}
+                    // Checkpoints take far longer than a propagation, so the
+                    // delivery is awaited without holding up later roots
+                    (_, Some(l2_block))
+                        if sent && self.bridge == BridgeKind::Polygon =>
+                    {
+                        tracing::info!(network = %self.name, root = %field, timeout = ?self.checkpoint_timeout, "Awaiting Polygon checkpoint for root delivery");
+                        awaiting_checkpoint = Some(field);
+                        tokio::spawn(
+                            await_root_added(
+                                self.name.clone(),
+                                self.labels.clone(),
+                                world_id.clone(),
+                                l2_block,
+                                field,
+                                self.checkpoint_timeout,
+                                CHECKPOINT_POLL_INTERVAL,
+                            )
+                            .in_current_span(),
+                        );
+                    }
+                    (
+                        ConfirmationConfig::RootAdded { timeout_secs },
+                        Some(l2_block),
+                    ) if sent => {
+                        await_root_added(
+                            self.name.clone(),
+                            self.labels.clone(),
+                            world_id.clone(),
+                            l2_block,
+                            field,
+                            Duration::from_secs(*timeout_secs),
+                            ROOT_ADDED_POLL_INTERVAL,
+                        )
+                        .await;
+                    }
+                    _ => {
+                        // We sleep for 2 blocks, so we don't resend the same root prior to derivation of the message on L2.
+                        tokio::time::sleep(self.propagation_backoff).await;
+
+                        match self.bridge {
+                            BridgeKind::OpStack if sent => {
+                                self.verify_delivery(&mut world_id, field)
+                                    .await;
+                            }
+                            // Delivery waits for the next checkpoint, so the
+                            // root can't be on the L2 yet
+                            BridgeKind::Polygon | BridgeKind::OpStack => {}
+                        }
                     }
                 }
-                // We sleep for 2 blocks, so we don't resend the same root prior to derivation of the message on L2.
-                std::thread::sleep(std::time::Duration::from_secs(
-                    ROOT_PROPAGATION_BACKOFF,
-                ));
             }
         }
     }
 }
 
+/// Waits for the L2 to emit `RootAdded` for the root, searching from
+/// `from_block`, and warns if it is not seen within the timeout.
+///
+/// Owns its arguments so slow deliveries can be awaited in the background.
+async fn await_root_added(
+    network: String,
+    labels: Vec<Label>,
+    world_id: BridgedWorldId,
+    from_block: u64,
+    root: Field,
+    timeout: Duration,
+    poll_interval: Duration,
+) {
+    let filter = Filter::new()
+        .address(*world_id.address())
+        .event_signature(RootAdded::SIGNATURE_HASH)
+        .from_block(from_block);
+    let deadline = Instant::now() + timeout;
+
+    loop {
+        match world_id.provider().get_logs(&filter).await {
+            Ok(logs) => {
+                let added = logs.iter().find_map(|log| {
+                    let event =
+                        RootAdded::decode_log(&log.inner, false).ok()?.data;
+                    (event.root == root)
+                        .then_some((log.block_number, event.timestamp))
+                });
+                if let Some((block_number, timestamp)) = added {
+                    tracing::info!(
+                        %network,
+                        %root,
+                        l2_block = ?block_number,
+                        timestamp,
+                        "Root delivered to L2"
+                    );
+                    return;
+                }
+            }
+            Err(error) => {
+                tracing::warn!(
+                    %network,
+                    %root,
+                    %error,
+                    "Failed to query L2 RootAdded logs"
+                );
+            }
+        }
+
+        if Instant::now() >= deadline {
+            tracing::warn!(
+                %network,
+                %root,
+                ?timeout,
+                "Timed out waiting for the L2 RootAdded event"
+            );
+            metrics::counter!("root_delivery_unconfirmed", labels).increment(1);
+            return;
+        }
+
+        tokio::time::sleep(poll_interval).await;
+    }
+}
+
+/// Relays roots to SVM networks, not supported yet.
+///
+/// Propagation needs the interface of the Solana state bridge program, its
+/// program id, accounts and instruction layout, which isn't defined yet. The
+/// signer holding the network's keypair is added along with it.
 pub struct SvmRelay;
 
 impl Relay for SvmRelay {
-    async fn subscribe_roots(&self, _rx: Receiver<Field>) -> Result<()> {
+    async fn subscribe_roots(&self, _rx: Receiver<RootUpdate>) -> Result<()> {
         unimplemented!()
     }
 }
 
 relay!(EVMRelay, SvmRelay);
+
+impl Relayer {
+    /// See [`EVMRelay::propagate_now`].
+    pub async fn propagate_now(&self) -> Result<(Field, Propagation)> {
+        match self {
+            Relayer::EVMRelay(relay) => relay.propagate_now().await,
+            Relayer::SvmRelay(_) => {
+                bail!("Manual propagation is only supported for EVM networks")
+            }
+        }
+    }
+
+    /// See [`EVMRelay::set_paused`].
+    pub fn set_paused(&self, paused: bool) -> Result<()> {
+        match self {
+            Relayer::EVMRelay(relay) => {
+                relay.set_paused(paused);
+                Ok(())
+            }
+            Relayer::SvmRelay(_) => {
+                bail!("Pausing is only supported for EVM networks")
+            }
+        }
+    }
+
+    pub fn restart_config(&self) -> RestartConfig {
+        match self {
+            Relayer::EVMRelay(relay) => relay.restart.clone(),
+            Relayer::SvmRelay(_) => RestartConfig::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use axum::http::StatusCode;
+    use axum::routing::post;
+    use axum::{Json, Router};
+    use serde_json::{json, Value};
+    use tokio::net::TcpListener;
+    use tokio::sync::broadcast;
+
+    use super::signer::MockSigner;
+    use super::*;
+
+    /// Serves the JSON-RPC methods a relay reads the L2 through, reporting
+    /// `l2_root` as the bridged World ID's latest root.
+    async fn stub_rpc(l2_root: Field) -> Url {
+        flaky_rpc(l2_root, 0).await
+    }
+
+    /// Like [`stub_rpc`], but fails the first `failures` calls with a server
+    /// error.
+    async fn flaky_rpc(l2_root: Field, failures: usize) -> Url {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let app = Router::new().route(
+            "/",
+            post(move |Json(request): Json<Value>| {
+                let failed = request["method"] == "eth_call"
+                    && calls.fetch_add(1, Ordering::SeqCst) < failures;
+                async move {
+                    if failed {
+                        return (
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            Json(json!({})),
+                        );
+                    }
+                    (StatusCode::OK, Json(rpc_response(&request, l2_root)))
+                }
+            }),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        url.parse().unwrap()
+    }
+
+    fn rpc_response(request: &Value, l2_root: Field) -> Value {
+        let result = match request["method"].as_str() {
+            Some("eth_chainId" | "eth_blockNumber") => json!("0x1"),
+            Some("eth_call") => {
+                json!(alloy::hex::encode_prefixed(l2_root.to_be_bytes::<32>()))
+            }
+            _ => {
+                return json!({
+                    "jsonrpc": "2.0",
+                    "id": request["id"],
+                    "error": { "code": -32601, "message": "method not found" },
+                })
+            }
+        };
+        json!({
+            "jsonrpc": "2.0",
+            "id": request["id"],
+            "result": result,
+        })
+    }
+
+    fn bridged_config(rpc: &Url, extra: &str) -> BridgedNetworkConfig {
+        toml::from_str(&format!(
+            r#"
+            name = "test"
+            type = "evm"
+            state_bridge_addr = "0x0000000000000000000000000000000000000001"
+            world_id_addr = "0x0000000000000000000000000000000000000002"
+            reconcile_on_startup = false
+            {extra}
+
+            [provider]
+            rpc_endpoint = "{rpc}"
+            "#
+        ))
+        .unwrap()
+    }
+
+    /// A relay to an L2 whose latest root is `l2_root`, propagating through
+    /// the mock.
+    async fn relay(l2_root: Field, extra: &str, mock: &MockSigner) -> EVMRelay {
+        relay_with_rpc(&stub_rpc(l2_root).await, extra, mock)
+    }
+
+    /// A relay to an L2 served at `rpc`, propagating through the mock.
+    fn relay_with_rpc(rpc: &Url, extra: &str, mock: &MockSigner) -> EVMRelay {
+        let bridged = bridged_config(rpc, extra);
+        let canonical = CanonicalWorldId {
+            world_id_address: Address::ZERO,
+            provider: bridged.provider.clone(),
+            initial_backoff: Duration::ZERO,
+            max_attempts: 1,
+            gated: false,
+        };
+        let mut relay = EVMRelay::new(
+            &bridged,
+            vec![(
+                bridged.state_bridge_addrs[0],
+                Signer::MockSigner(mock.clone()),
+            )],
+            canonical,
+            RelayEvents::new(),
+            Arc::new(NetworkHealth::new(None)),
+            RelayStatus::new(),
+        );
+        relay.propagation_backoff = Duration::ZERO;
+        relay
+    }
+
+    /// Streams the roots to the relay and waits for it to handle all of them.
+    async fn stream_roots(relay: &EVMRelay, roots: &[u64]) {
+        let (tx, rx) = broadcast::channel(roots.len().max(1));
+        for root in roots {
+            tx.send(RootUpdate::latest(Field::from(*root))).unwrap();
+        }
+        drop(tx);
+        relay.subscribe_roots(rx).await.unwrap();
+    }
+
+    /// Serves `root` as the latest canonical root.
+    async fn set_canonical_root(relay: &mut EVMRelay, root: Field) {
+        relay.canonical.provider.rpc_endpoints = vec![stub_rpc(root).await];
+    }
+
+    #[tokio::test]
+    async fn propagates_roots_missing_on_l2() {
+        let mock = MockSigner::new();
+        let relay = relay(Field::from(1), "", &mock).await;
+
+        stream_roots(&relay, &[2]).await;
+
+        assert_eq!(mock.roots(), vec![Field::from(2)]);
+        let status = &relay.status.snapshot()["test"];
+        assert_eq!(status.last_propagated_root, Some(Field::from(2)));
+    }
+
+    #[tokio::test]
+    async fn skips_roots_already_on_l2() {
+        let mock = MockSigner::new();
+        let relay = relay(Field::from(2), "", &mock).await;
+
+        stream_roots(&relay, &[2]).await;
+
+        assert!(mock.roots().is_empty());
+        assert!(relay.status.snapshot()["test"].in_sync);
+    }
+
+    #[tokio::test]
+    async fn skips_roots_awaiting_delivery() {
+        let mock = MockSigner::new();
+        let relay = relay(Field::from(1), "", &mock).await;
+
+        stream_roots(&relay, &[2, 2, 3]).await;
+
+        assert_eq!(mock.roots(), vec![Field::from(2), Field::from(3)]);
+    }
+
+    #[tokio::test]
+    async fn retries_failed_roots() {
+        let mock = MockSigner::new();
+        mock.respond_with(Err("execution reverted".to_owned()));
+        let relay = relay(Field::from(1), "", &mock).await;
+
+        stream_roots(&relay, &[2, 2]).await;
+
+        assert_eq!(mock.roots(), vec![Field::from(2), Field::from(2)]);
+    }
+
+    #[tokio::test]
+    async fn dry_runs_are_not_recorded_as_propagated() {
+        let mock = MockSigner::new();
+        mock.respond_with(Ok(Propagation::DryRun));
+        mock.respond_with(Ok(Propagation::DryRun));
+        let relay = relay(Field::from(1), "", &mock).await;
+
+        stream_roots(&relay, &[2, 2]).await;
+
+        // Nothing was sent, so the root is not awaiting delivery either
+        assert_eq!(mock.roots(), vec![Field::from(2), Field::from(2)]);
+        let status = &relay.status.snapshot()["test"];
+        assert_eq!(status.last_propagated_root, None);
+        assert_eq!(status.last_error, None);
+    }
+
+    #[tokio::test]
+    async fn retries_skipped_roots_without_failing() {
+        let mock = MockSigner::new();
+        mock.respond_with(Ok(Propagation::Skipped));
+        let dir = tempfile::tempdir().unwrap();
+        let dead_letters = DeadLetters::new(dir.path().join("dead.jsonl"));
+        let mut relay = relay(
+            Field::from(1),
+            "circuit_breaker = { failure_threshold = 1, cooldown_secs = 3600 }",
+            &mock,
+        )
+        .await
+        .with_dead_letters(Some(dead_letters.clone()));
+        relay.skipped_retry = Duration::ZERO;
+
+        // The stream stays open, so only the retry can propagate the root again
+        let (tx, rx) = broadcast::channel(1);
+        tx.send(RootUpdate::latest(Field::from(2))).unwrap();
+        let retried = async move {
+            while mock.roots().len() < 2 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+            drop(tx);
+            mock
+        };
+        let (result, mock) =
+            tokio::time::timeout(Duration::from_secs(5), async {
+                tokio::join!(relay.subscribe_roots(rx), retried)
+            })
+            .await
+            .expect("skipped root was not retried");
+        result.unwrap();
+
+        assert_eq!(mock.roots()[..2], [Field::from(2), Field::from(2)]);
+        let status = &relay.status.snapshot()["test"];
+        assert!(!status.circuit_open);
+        assert_eq!(status.last_error, None);
+        assert!(dead_letters.read().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn reconciles_the_latest_canonical_root_on_startup() {
+        let mock = MockSigner::new();
+        let mut relay = relay(Field::from(1), "", &mock).await;
+        set_canonical_root(&mut relay, Field::from(5)).await;
+        relay.reconcile_on_startup = true;
+
+        stream_roots(&relay, &[]).await;
+
+        assert_eq!(mock.roots(), vec![Field::from(5)]);
+    }
+
+    #[tokio::test]
+    async fn gated_roots_are_not_reconciled_on_startup() {
+        let mock = MockSigner::new();
+        let mut relay = relay(Field::from(1), "", &mock).await;
+        set_canonical_root(&mut relay, Field::from(5)).await;
+        relay.reconcile_on_startup = true;
+        relay.canonical.gated = true;
+
+        stream_roots(&relay, &[]).await;
+
+        assert!(mock.roots().is_empty());
+    }
+
+    /// Streams the roots through a channel too small to hold them all, so
+    /// the relay lags behind.
+    async fn stream_roots_lagging(relay: &EVMRelay, roots: &[u64]) {
+        let (tx, rx) = broadcast::channel(1);
+        for root in roots {
+            tx.send(RootUpdate::latest(Field::from(*root))).unwrap();
+        }
+        drop(tx);
+        relay.subscribe_roots(rx).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn lagging_relay_propagates_the_latest_canonical_root() {
+        let mock = MockSigner::new();
+        let mut relay = relay(Field::from(1), "", &mock).await;
+        set_canonical_root(&mut relay, Field::from(5)).await;
+
+        stream_roots_lagging(&relay, &[2, 3]).await;
+
+        assert_eq!(mock.roots(), vec![Field::from(5), Field::from(3)]);
+    }
+
+    #[tokio::test]
+    async fn receiving_recovers_from_lag() {
+        let mock = MockSigner::new();
+        let mut relay = relay(Field::from(1), "", &mock).await;
+        set_canonical_root(&mut relay, Field::from(5)).await;
+        let canonical_world_id = relay.canonical_world_id();
+
+        let (tx, mut rx) = broadcast::channel(1);
+        for root in [2, 3, 4] {
+            tx.send(RootUpdate::latest(Field::from(root))).unwrap();
+        }
+        drop(tx);
+
+        let mut received = Vec::new();
+        while let Some(update) =
+            relay.recv_root(&mut rx, &canonical_world_id).await.unwrap()
+        {
+            received.push(update.post_root);
+        }
+
+        assert_eq!(received, vec![Field::from(5), Field::from(4)]);
+    }
+
+    #[tokio::test]
+    async fn lagging_gated_relay_continues_from_buffered_roots() {
+        let mock = MockSigner::new();
+        let mut relay = relay(Field::from(1), "", &mock).await;
+        set_canonical_root(&mut relay, Field::from(5)).await;
+        relay.canonical.gated = true;
+
+        stream_roots_lagging(&relay, &[2, 3]).await;
+
+        assert_eq!(mock.roots(), vec![Field::from(3)]);
+    }
+
+    #[tokio::test]
+    async fn records_failed_propagations() {
+        let dir = tempfile::tempdir().unwrap();
+        let dead_letters = DeadLetters::new(dir.path().join("dead.jsonl"));
+        let mock = MockSigner::new();
+        mock.respond_with(Err("execution reverted".to_owned()));
+        let relay = relay(Field::from(1), "", &mock)
+            .await
+            .with_dead_letters(Some(dead_letters.clone()));
+
+        stream_roots(&relay, &[2]).await;
+
+        assert_eq!(mock.roots(), vec![Field::from(2)]);
+        let entries = dead_letters.read().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].root, Field::from(2));
+        assert!(entries[0].error.contains("execution reverted"));
+        let status = &relay.status.snapshot()["test"];
+        assert!(status.last_error.is_some());
+        assert_eq!(status.last_propagated_root, None);
+    }
+
+    #[tokio::test]
+    async fn open_circuit_skips_propagation() {
+        let mock = MockSigner::new();
+        mock.respond_with(Err("execution reverted".to_owned()));
+        mock.respond_with(Err("execution reverted".to_owned()));
+        let relay = relay(
+            Field::from(1),
+            "circuit_breaker = { failure_threshold = 2, cooldown_secs = 3600 }",
+            &mock,
+        )
+        .await;
+
+        stream_roots(&relay, &[2, 3, 4]).await;
+
+        assert_eq!(mock.roots(), vec![Field::from(2), Field::from(3)]);
+        assert!(relay.status.snapshot()["test"].circuit_open);
+    }
+
+    #[tokio::test]
+    async fn l2_reads_recover_from_transport_failures() {
+        let mock = MockSigner::new();
+        let rpc = flaky_rpc(Field::from(1), 2).await;
+        let relay = relay_with_rpc(
+            &rpc,
+            "reconnect = { initial_backoff_ms = 1, max_attempts = 2 }",
+            &mock,
+        );
+
+        let mut world_id = relay.l2_world_id().await.unwrap();
+        let root = relay.l2_latest_root(&mut world_id).await.unwrap();
+
+        assert_eq!(root, Field::from(1));
+    }
+
+    #[tokio::test]
+    async fn l2_reads_give_up_after_max_attempts() {
+        let mock = MockSigner::new();
+        let rpc = flaky_rpc(Field::from(1), 3).await;
+        let relay = relay_with_rpc(
+            &rpc,
+            "reconnect = { initial_backoff_ms = 1, max_attempts = 2 }",
+            &mock,
+        );
+
+        let mut world_id = relay.l2_world_id().await.unwrap();
+        assert!(relay.l2_latest_root(&mut world_id).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn canonical_reads_retry_a_flaky_provider() {
+        let mock = MockSigner::new();
+        let mut relay = relay(Field::from(1), "", &mock).await;
+        relay.canonical.provider.rpc_endpoints =
+            vec![flaky_rpc(Field::from(2), 2).await];
+        relay.canonical.max_attempts = 3;
+
+        let root = relay
+            .canonical_latest_root(&relay.canonical_world_id())
+            .await
+            .unwrap();
+
+        assert_eq!(root, Field::from(2));
+    }
+
+    #[tokio::test]
+    async fn canonical_reads_give_up_after_max_attempts() {
+        let mock = MockSigner::new();
+        let mut relay = relay(Field::from(1), "", &mock).await;
+        relay.canonical.provider.rpc_endpoints =
+            vec![flaky_rpc(Field::from(2), 2).await];
+        relay.canonical.max_attempts = 2;
+
+        assert!(relay
+            .canonical_latest_root(&relay.canonical_world_id())
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn cancelled_manual_propagation_clears_in_flight() {
+        let mock = MockSigner::new();
+        mock.delay_by(Duration::from_secs(3600));
+        let mut relay = relay(Field::from(1), "", &mock).await;
+        set_canonical_root(&mut relay, Field::from(5)).await;
+
+        // As when the HTTP client disconnects mid propagation
+        let cancelled = tokio::time::timeout(
+            Duration::from_millis(200),
+            relay.propagate_now(),
+        )
+        .await;
+        assert!(cancelled.is_err());
+        assert_eq!(*relay.in_flight.lock().unwrap(), None);
+
+        mock.delay_by(Duration::ZERO);
+        let (root, _) = relay.propagate_now().await.unwrap();
+        assert_eq!(root, Field::from(5));
+    }
+
+    #[tokio::test]
+    async fn manual_propagation_fails_while_another_is_in_flight() {
+        let mock = MockSigner::new();
+        let mut relay = relay(Field::from(1), "", &mock).await;
+        set_canonical_root(&mut relay, Field::from(5)).await;
+
+        let in_flight = InFlight::new(
+            &relay,
+            Field::from(2),
+            relay.propagating.lock().await,
+        );
+        let error = relay.propagate_now().await.unwrap_err();
+        assert!(error.to_string().contains("already in flight"), "{error}");
+        drop(in_flight);
+
+        relay.propagate_now().await.unwrap();
+        assert_eq!(mock.roots(), vec![Field::from(5)]);
+    }
+
+    #[tokio::test]
+    async fn streamed_propagation_waits_for_manual_one() {
+        let delay = Duration::from_millis(200);
+        let mock = MockSigner::new();
+        mock.delay_by(delay);
+        let mut relay = relay(Field::from(1), "", &mock).await;
+        set_canonical_root(&mut relay, Field::from(5)).await;
+
+        let (manual, ()) = tokio::join!(relay.propagate_now(), async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            stream_roots(&relay, &[2]).await;
+        });
+        manual.unwrap();
+
+        let calls = mock.calls();
+        assert_eq!(mock.roots(), vec![Field::from(5), Field::from(2)]);
+        assert!(calls[1].1 - calls[0].1 >= delay);
+    }
+
+    #[tokio::test]
+    async fn propagates_the_held_root_when_the_stream_closes() {
+        let mock = MockSigner::new();
+        let mut relay = relay(Field::from(1), "", &mock).await;
+        relay.min_propagation_interval = Some(Duration::from_millis(100));
+
+        stream_roots(&relay, &[2, 3, 4]).await;
+
+        assert_eq!(mock.roots(), vec![Field::from(2), Field::from(4)]);
+    }
+}
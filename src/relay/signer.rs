@@ -1,38 +1,83 @@
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use alloy::network::{Ethereum, EthereumWallet};
-use alloy::primitives::{bytes, Address, Bytes};
+use alloy::consensus::Transaction as _;
+use alloy::network::{Ethereum, EthereumWallet, Network, TransactionBuilder};
+use alloy::primitives::{bytes, keccak256, Address, Bytes, TxHash, U256};
 use alloy::providers::fillers::{
     BlobGasFiller, CachedNonceManager, ChainIdFiller, FillProvider, GasFiller,
-    JoinFill, NonceFiller, WalletFiller,
+    JoinFill, NonceFiller, NonceManager, SimpleNonceManager, WalletFiller,
 };
-use alloy::providers::{Identity, RootProvider};
-use ethers_core::types::U256;
+use alloy::providers::{Identity, Provider, RootProvider};
+use alloy::rpc::types::{TransactionReceipt, TransactionRequest};
+use alloy::signers::local::PrivateKeySigner;
+use alloy::signers::Signer as _;
+use alloy::transports::http::{Client, Http};
+use alloy::transports::{Transport, TransportResult};
+use async_trait::async_trait;
 use eyre::eyre::{eyre, Result};
-use tracing::{debug, error, info};
-use tx_sitter_client::data::{SendTxRequest, TransactionPriority, TxStatus};
-use tx_sitter_client::TxSitterClient;
+use semaphore::Field;
+use telemetry_batteries::reexports::metrics::{self, Label};
+use tracing::{debug, error, info, info_span, warn, Instrument};
+use tx_sitter_client::data::{SendTxRequest, TransactionPriority};
 
 use crate::abi::IStateBridge::IStateBridgeInstance;
-use crate::config::ThrottledTransport;
+use crate::config::{
+    BridgedNetworkConfig, NonceManagerKind, ReplacementConfig,
+    ThrottledTransport, TxPriority,
+};
+use crate::safe::{SafeServiceClient, SafeTx};
+use crate::tx_sitter::{monitor_tx, TxReverted, TxSitterClient};
+
+/// Interval between receipt polls while waiting to replace a transaction
+pub const RECEIPT_POLL_INTERVAL: Duration = Duration::from_secs(4);
 
 /// keccak256("propagateRoot()")[..4]
 pub static PROPAGATE_ROOT_SELECTOR: Bytes = bytes!("380db829");
 
+/// The outcome of a propagation that didn't fail
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Propagation {
+    /// The propagation transaction with this hash was mined
+    Sent(TxHash),
+    /// Handed off without a transaction of the relay's own, e.g. proposed to
+    /// a Safe
+    Submitted,
+    /// Logged instead of sent, the relay is running dry
+    DryRun,
+    /// Postponed as the gas price exceeds the wallet ceiling or the fee
+    /// exceeds the fee cap
+    Skipped,
+}
+
+impl Propagation {
+    /// The hash of the propagation transaction, if one was sent.
+    pub fn tx_hash(&self) -> Option<TxHash> {
+        match self {
+            Self::Sent(tx_hash) => Some(*tx_hash),
+            Self::Submitted | Self::DryRun | Self::Skipped => None,
+        }
+    }
+}
+
 pub(crate) trait RelaySigner {
     /// Propogate a new Root to the State Bridge for the given network.
-    async fn propagate_root(&self) -> Result<()>;
+    ///
+    /// The state bridge always propagates its current root, `root` is the
+    /// canonical root expected to be propagated.
+    async fn propagate_root(&self, root: Field) -> Result<Propagation>;
 }
 
 macro_rules! signer {
-    ($($signer_type:ident),+ $(,)?) => {
+    ($($(#[$meta:meta])* $signer_type:ident),+ $(,)?) => {
         pub enum Signer {
-            $($signer_type($signer_type),)+
+            $($(#[$meta])* $signer_type($signer_type),)+
         }
         impl RelaySigner for Signer {
-            async fn propagate_root(&self) -> Result<()> {
+            async fn propagate_root(&self, root: Field) -> Result<Propagation> {
                 match self {
-                    $(Signer::$signer_type(signer) => signer.propagate_root().await,)+
+                    $($(#[$meta])* Signer::$signer_type(signer) => signer.propagate_root(root).await,)+
                 }
             }
         }
@@ -43,10 +88,53 @@ pub type TxFillers = JoinFill<
     GasFiller,
     JoinFill<
         BlobGasFiller,
-        JoinFill<NonceFiller<CachedNonceManager>, ChainIdFiller>,
+        JoinFill<NonceFiller<RelayNonceManager>, ChainIdFiller>,
     >,
 >;
 
+/// The nonce manager of locally signing providers, see [`NonceManagerKind`]
+#[derive(Clone, Debug)]
+pub enum RelayNonceManager {
+    Cached(CachedNonceManager),
+    Simple(SimpleNonceManager),
+}
+
+impl From<NonceManagerKind> for RelayNonceManager {
+    fn from(kind: NonceManagerKind) -> Self {
+        match kind {
+            NonceManagerKind::Cached => {
+                Self::Cached(CachedNonceManager::default())
+            }
+            NonceManagerKind::Simple => {
+                Self::Simple(SimpleNonceManager::default())
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl NonceManager for RelayNonceManager {
+    async fn get_next_nonce<P, T, N>(
+        &self,
+        provider: &P,
+        address: Address,
+    ) -> TransportResult<u64>
+    where
+        P: Provider<T, N>,
+        N: Network,
+        T: Transport + Clone,
+    {
+        match self {
+            Self::Cached(manager) => {
+                manager.get_next_nonce(provider, address).await
+            }
+            Self::Simple(manager) => {
+                manager.get_next_nonce(provider, address).await
+            }
+        }
+    }
+}
+
 pub type AlloySignerProvider = FillProvider<
     JoinFill<JoinFill<Identity, TxFillers>, WalletFiller<EthereumWallet>>,
     RootProvider<ThrottledTransport>,
@@ -54,71 +142,530 @@ pub type AlloySignerProvider = FillProvider<
     Ethereum,
 >;
 
+/// Overrides applied to locally signed propagation transactions
+///
+/// The `GasFiller` only leaves a transaction alone once its gas limit and
+/// both EIP-1559 fee fields are set, otherwise it replaces all of them with
+/// its own estimates. When any field is overridden the remaining fields are
+/// therefore estimated up front so the overrides survive filling. The
+/// `BlobGasFiller` only acts on blob transactions and is unaffected.
+#[derive(Clone, Debug, Default)]
+pub struct TxOverrides {
+    /// Replaces the gas limit estimated by the `GasFiller`
+    pub gas_limit: Option<u64>,
+    /// Replaces the estimated `maxFeePerGas`
+    pub max_fee_per_gas: Option<u128>,
+    /// Replaces the estimated `maxPriorityFeePerGas`
+    pub max_priority_fee_per_gas: Option<u128>,
+}
+
 pub struct AlloySigner {
+    pub network: String,
     pub state_bridge_address: Address,
     pub provider: Arc<AlloySignerProvider>,
+    /// The maximum total fee in wei a single propagation may spend
+    pub max_tx_fee: Option<U256>,
+    /// Propagations are skipped while the gas price in wei exceeds this
+    pub max_gas_price: Option<u128>,
+    /// Overrides applied to the propagation transaction
+    pub overrides: TxOverrides,
+    /// How to replace the propagation transaction if it gets stuck
+    pub replacement: Option<ReplacementConfig>,
+    /// Simulate the propagation with `eth_call` before sending it
+    pub simulate: bool,
+    /// Blocks deep the receipt must be before the propagation succeeds
+    pub confirmations: u64,
+    /// Labels of the signer's metrics, the network name and configured tags
+    pub labels: Vec<Label>,
+    /// Log the propagation transaction instead of sending it
+    pub dry_run: bool,
 }
 
 impl AlloySigner {
     pub fn new(
+        bridged: &BridgedNetworkConfig,
         state_bridge_address: Address,
         provider: Arc<AlloySignerProvider>,
+        overrides: TxOverrides,
+        confirmations: u64,
+        dry_run: bool,
     ) -> Self {
         Self {
+            network: bridged.name.clone(),
             state_bridge_address,
             provider,
+            max_tx_fee: bridged.max_tx_fee_wei,
+            max_gas_price: None,
+            overrides,
+            replacement: bridged.replacement.clone(),
+            simulate: bridged.simulate,
+            confirmations,
+            labels: bridged.metric_labels(),
+            dry_run,
+        }
+    }
+
+    /// Skips propagations while the gas price in wei exceeds the ceiling.
+    pub fn with_max_gas_price(mut self, max_gas_price: Option<u128>) -> Self {
+        self.max_gas_price = max_gas_price;
+        self
+    }
+
+    /// The gas price in wei if it exceeds the wallet ceiling, replacements
+    /// are postponed while it does.
+    async fn gas_price_above_ceiling(&self) -> Result<Option<u128>> {
+        let Some(max_gas_price) = self.max_gas_price else {
+            return Ok(None);
+        };
+        let gas_price = self.provider.get_gas_price().await?;
+        Ok((gas_price > max_gas_price).then_some(gas_price))
+    }
+
+    /// Waits until the block including the transaction is
+    /// [`Self::confirmations`] deep.
+    ///
+    /// The receipt is fetched again on every poll, so a transaction reorged
+    /// into a later block is followed there and one reorged out keeps the
+    /// propagation waiting.
+    async fn await_confirmations(&self, tx_hash: TxHash) -> Result<()> {
+        loop {
+            let receipt =
+                self.provider.get_transaction_receipt(tx_hash).await?;
+            if let Some(block) =
+                receipt.and_then(|receipt| receipt.block_number)
+            {
+                let latest = self.provider.get_block_number().await?;
+                let depth = (latest + 1).saturating_sub(block);
+                if depth >= self.confirmations {
+                    debug!(network = %self.network, %tx_hash, block, depth, "Root propagation confirmed");
+                    return Ok(());
+                }
+            }
+
+            tokio::time::sleep(RECEIPT_POLL_INTERVAL).await;
+        }
+    }
+
+    /// Fails the propagation if its transaction was mined but reverted.
+    fn check_status(&self, receipt: &TransactionReceipt) -> Result<()> {
+        if receipt.status() {
+            return Ok(());
+        }
+
+        metrics::counter!("propagation_reverted_total", self.labels.clone())
+            .increment(1);
+        error!(
+            network = %self.network,
+            tx_hash = %receipt.transaction_hash,
+            block_number = ?receipt.block_number,
+            "Root propagation transaction reverted"
+        );
+        Err(eyre!(
+            "Root propagation transaction {} reverted",
+            receipt.transaction_hash
+        ))
+    }
+
+    /// The hash of whichever of the transactions was mined, if any.
+    async fn mined(&self, hashes: &[TxHash]) -> Result<Option<TxHash>> {
+        for hash in hashes {
+            if let Some(receipt) =
+                self.provider.get_transaction_receipt(*hash).await?
+            {
+                record_gas_cost(&self.network, &self.labels, &receipt);
+                self.check_status(&receipt)?;
+                debug!(receipt = ?receipt, "Successfully propogated Root to State Bridge.");
+                return Ok(Some(*hash));
+            }
         }
+        Ok(None)
     }
+
+    /// Waits for the transaction to be mined, replacing it with higher fees
+    /// under the same nonce whenever no receipt appears within the timeout.
+    ///
+    /// Returns the hash of whichever transaction was mined.
+    async fn await_receipt_or_replace(
+        &self,
+        tx_hash: TxHash,
+        replacement: &ReplacementConfig,
+    ) -> Result<TxHash> {
+        let sent = self
+            .provider
+            .get_transaction_by_hash(tx_hash)
+            .await?
+            .ok_or_else(|| eyre!("Sent transaction {tx_hash} not found"))?;
+        let timeout = Duration::from_secs(replacement.replacement_timeout_secs);
+
+        // Any of the sent transactions may be the one that gets mined
+        let mut hashes = vec![tx_hash];
+        let mut max_fee_per_gas = sent.max_fee_per_gas();
+        let mut max_priority_fee_per_gas =
+            sent.max_priority_fee_per_gas().unwrap_or_default();
+        let mut bumps = 0;
+        let mut deadline = Instant::now() + timeout;
+
+        loop {
+            if let Some(hash) = self.mined(&hashes).await? {
+                return Ok(hash);
+            }
+
+            if Instant::now() < deadline || bumps >= replacement.max_fee_bumps {
+                tokio::time::sleep(RECEIPT_POLL_INTERVAL).await;
+                continue;
+            }
+
+            let new_max_fee_per_gas =
+                bump_fee(max_fee_per_gas, replacement.fee_bump_percent);
+            let new_max_priority_fee_per_gas = bump_fee(
+                max_priority_fee_per_gas,
+                replacement.fee_bump_percent,
+            );
+
+            // Replacements are held to the same caps as the original
+            if let Some(gas_price) = self.gas_price_above_ceiling().await? {
+                warn!(
+                    network = %self.network,
+                    nonce = sent.nonce(),
+                    gas_price,
+                    max_gas_price = ?self.max_gas_price,
+                    "Gas price exceeds the wallet ceiling, postponing replacement"
+                );
+                deadline = Instant::now() + timeout;
+                continue;
+            }
+            if let Some(fee) = fee_above_cap(
+                sent.gas_limit(),
+                new_max_fee_per_gas,
+                self.max_tx_fee,
+            ) {
+                metrics::counter!(
+                    "propagation_fee_cap_exceeded",
+                    self.labels.clone()
+                )
+                .increment(1);
+                warn!(
+                    network = %self.network,
+                    nonce = sent.nonce(),
+                    %fee,
+                    max_tx_fee = ?self.max_tx_fee,
+                    "Replacement fee exceeds the wallet fee cap, no longer replacing"
+                );
+                // Fees only grow with every bump
+                bumps = replacement.max_fee_bumps;
+                continue;
+            }
+
+            // The nonce is set explicitly, so the nonce manager is bypassed
+            let request = TransactionRequest::default()
+                .with_to(self.state_bridge_address)
+                .with_input(sent.input().clone())
+                .with_nonce(sent.nonce())
+                .with_gas_limit(sent.gas_limit())
+                .with_max_fee_per_gas(new_max_fee_per_gas)
+                .with_max_priority_fee_per_gas(new_max_priority_fee_per_gas);
+            let pending = match self.provider.send_transaction(request).await {
+                Ok(pending) => pending,
+                // One of the sent transactions may have been mined since the
+                // receipts were polled, spending the nonce
+                Err(error) => match self.mined(&hashes).await? {
+                    Some(hash) => return Ok(hash),
+                    None => return Err(error.into()),
+                },
+            };
+
+            bumps += 1;
+            info!(
+                network = %self.network,
+                nonce = sent.nonce(),
+                bump = bumps,
+                old_max_fee_per_gas = max_fee_per_gas,
+                new_max_fee_per_gas,
+                old_max_priority_fee_per_gas = max_priority_fee_per_gas,
+                new_max_priority_fee_per_gas,
+                tx_hash = %pending.tx_hash(),
+                "Replaced stuck root propagation transaction"
+            );
+
+            hashes.push(*pending.tx_hash());
+            max_fee_per_gas = new_max_fee_per_gas;
+            max_priority_fee_per_gas = new_max_priority_fee_per_gas;
+            deadline = Instant::now() + timeout;
+        }
+    }
+}
+
+/// The worst case fee of a transaction if it exceeds the cap, the base fee
+/// may settle lower.
+fn fee_above_cap(
+    gas: u64,
+    max_fee_per_gas: u128,
+    max_tx_fee: Option<U256>,
+) -> Option<U256> {
+    let fee = U256::from(gas) * U256::from(max_fee_per_gas);
+    max_tx_fee
+        .is_some_and(|max_tx_fee| fee > max_tx_fee)
+        .then_some(fee)
+}
+
+/// Logs and records the fee paid by a mined propagation transaction.
+fn record_gas_cost(
+    network: &str,
+    labels: &[Label],
+    receipt: &TransactionReceipt,
+) {
+    let fee = receipt.gas_used.saturating_mul(receipt.effective_gas_price);
+    info!(
+        network,
+        tx_hash = %receipt.transaction_hash,
+        gas_used = receipt.gas_used,
+        effective_gas_price = receipt.effective_gas_price,
+        fee,
+        "Root propagation gas cost"
+    );
+    metrics::histogram!("propagation_gas_cost_wei", labels.to_vec())
+        .record(fee as f64);
+}
+
+/// Raises the fee by the given percentage, and by at least one wei.
+fn bump_fee(fee: u128, percent: u64) -> u128 {
+    let bumped = fee.saturating_mul(100 + u128::from(percent)) / 100;
+    bumped.max(fee + 1)
 }
 
 impl RelaySigner for AlloySigner {
-    async fn propagate_root(&self) -> Result<()> {
+    async fn propagate_root(&self, _root: Field) -> Result<Propagation> {
+        // `propagateRoot()` carries the latest root, so a skipped root is
+        // propagated along with the next one sent once fees drop
+        if let Some(max_gas_price) = self.max_gas_price {
+            let gas_price = self.provider.get_gas_price().await?;
+            if gas_price > max_gas_price {
+                metrics::counter!(
+                    "propagation_skipped_high_gas_total",
+                    self.labels.clone()
+                )
+                .increment(1);
+                warn!(
+                    network = %self.network,
+                    gas_price,
+                    max_gas_price,
+                    "Gas price exceeds the wallet ceiling, skipping propagation"
+                );
+                return Ok(Propagation::Skipped);
+            }
+        }
+
         let state_bridge_instance = IStateBridgeInstance::new(
             self.state_bridge_address,
             self.provider.clone(),
         );
 
-        let transport = state_bridge_instance.propagateRoot().send().await?;
+        let mut call = state_bridge_instance.propagateRoot();
+        if self.overrides.gas_limit.is_some()
+            || self.overrides.max_fee_per_gas.is_some()
+            || self.overrides.max_priority_fee_per_gas.is_some()
+        {
+            let gas_limit = match self.overrides.gas_limit {
+                Some(gas_limit) => gas_limit,
+                None => call.estimate_gas().await?,
+            };
+            call = call.gas(gas_limit);
+            let fees = self.provider.estimate_eip1559_fees(None).await?;
+            call = call
+                .max_fee_per_gas(
+                    self.overrides
+                        .max_fee_per_gas
+                        .unwrap_or(fees.max_fee_per_gas),
+                )
+                .max_priority_fee_per_gas(
+                    self.overrides
+                        .max_priority_fee_per_gas
+                        .unwrap_or(fees.max_priority_fee_per_gas),
+                );
+        }
+
+        if self.simulate {
+            call.call().await.map_err(|e| {
+                eyre!(
+                    "Simulated propagation to {} reverted, not sending it: {e}",
+                    self.network
+                )
+            })?;
+        }
 
-        match transport.get_receipt().await {
-            Ok(receipt) => {
-                debug!(receipt = ?receipt, "Successfully propogated Root to State Bridge.");
+        if self.dry_run {
+            let gas_limit = match self.overrides.gas_limit {
+                Some(gas_limit) => gas_limit,
+                None => call.estimate_gas().await?,
+            };
+            info!(
+                network = %self.network,
+                state_bridge_address = %self.state_bridge_address,
+                selector = %PROPAGATE_ROOT_SELECTOR,
+                gas_limit,
+                "Dry run, not sending root propagation transaction"
+            );
+            return Ok(Propagation::DryRun);
+        }
+
+        if let Some(max_tx_fee) = self.max_tx_fee {
+            let gas = match self.overrides.gas_limit {
+                Some(gas_limit) => gas_limit,
+                None => call.estimate_gas().await?,
+            };
+            let max_fee_per_gas = match self.overrides.max_fee_per_gas {
+                Some(max_fee_per_gas) => max_fee_per_gas,
+                None => {
+                    self.provider
+                        .estimate_eip1559_fees(None)
+                        .await?
+                        .max_fee_per_gas
+                }
+            };
+
+            if let Some(fee) =
+                fee_above_cap(gas, max_fee_per_gas, Some(max_tx_fee))
+            {
+                metrics::counter!(
+                    "propagation_fee_cap_exceeded",
+                    self.labels.clone()
+                )
+                .increment(1);
+                error!(
+                    network = %self.network,
+                    %fee,
+                    %max_tx_fee,
+                    "Estimated propagation fee exceeds the wallet fee cap, skipping propagation"
+                );
+                return Ok(Propagation::Skipped);
             }
-            Err(e) => {
-                error!(error = ?e, "Failed to propogate Root to State Bridge.");
+        }
+
+        let transport = call.send().await?;
+        let tx_hash = *transport.tx_hash();
+
+        let tx_hash = match &self.replacement {
+            Some(replacement) => {
+                self.await_receipt_or_replace(tx_hash, replacement).await?
+            }
+            None => {
+                match transport.get_receipt().await {
+                    Ok(receipt) => {
+                        record_gas_cost(&self.network, &self.labels, &receipt);
+                        self.check_status(&receipt)?;
+                        debug!(receipt = ?receipt, "Successfully propogated Root to State Bridge.");
+                    }
+                    // The outcome is unknown, the relay re-reads the L2 root
+                    // before propagating again
+                    Err(e) => {
+                        error!(error = ?e, "Failed to propogate Root to State Bridge.");
+                        return Err(eyre!(
+                            "Failed to get the receipt of propagation transaction {tx_hash}: {e}"
+                        ));
+                    }
+                }
+                tx_hash
             }
+        };
+
+        if self.confirmations > 1 {
+            self.await_confirmations(tx_hash).await?;
         }
 
-        Ok(())
+        Ok(Propagation::Sent(tx_hash))
     }
 }
 
 pub struct TxSitterSigner {
+    network: String,
+    /// Labels of the signer's metrics, the network name and configured tags
+    labels: Vec<Label>,
     tx_sitter: TxSitterClient,
     state_bridge_address: Address,
     gas_limit: Option<u64>,
+    tx_timeout: Duration,
+    /// The interval before the first status poll
+    poll_interval: Duration,
+    /// The interval between status polls never grows beyond this
+    max_poll_interval: Duration,
+    /// Cross-checks mined transactions against receipts from this provider
+    receipt_provider: Option<RootProvider<Http<Client>>>,
+    priority: TxPriority,
+    /// Pending transactions are logged at info every this many status polls
+    pending_log_every: Option<u32>,
+    /// Transactions per root that reverted, salting the id of the next one
+    reverted: std::sync::Mutex<HashMap<Field, u32>>,
+    dry_run: bool,
 }
 
 impl TxSitterSigner {
     pub fn new(
-        url: &str,
+        bridged: &BridgedNetworkConfig,
         state_bridge_address: Address,
+        tx_sitter: TxSitterClient,
         gas_limit: Option<u64>,
+        tx_timeout: Duration,
+        poll_interval: Duration,
+        dry_run: bool,
     ) -> Self {
-        let tx_sitter = TxSitterClient::new(url);
         Self {
+            network: bridged.name.clone(),
+            labels: bridged.metric_labels(),
             tx_sitter,
             state_bridge_address,
             gas_limit,
+            tx_timeout,
+            poll_interval,
+            max_poll_interval: poll_interval,
+            receipt_provider: None,
+            priority: TxPriority::default(),
+            pending_log_every: None,
+            reverted: std::sync::Mutex::default(),
+            dry_run,
         }
     }
-}
 
-impl RelaySigner for TxSitterSigner {
-    /// Propogate a new Root to the given network.
-    ///
-    /// This is a long running operation and should probably be awaited in a background task.
-    async fn propagate_root(&self) -> Result<()> {
+    /// Cross-checks mined transactions against receipts from the provider.
+    pub fn with_receipt_provider(
+        mut self,
+        receipt_provider: Option<RootProvider<Http<Client>>>,
+    ) -> Self {
+        self.receipt_provider = receipt_provider;
+        self
+    }
+
+    /// Lets the interval between status polls double up to `max`.
+    pub fn with_max_poll_interval(mut self, max: Duration) -> Self {
+        self.max_poll_interval = max.max(self.poll_interval);
+        self
+    }
+
+    /// Sends propagations with `priority` instead of [`TxPriority::Fast`].
+    pub fn with_priority(mut self, priority: TxPriority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Logs still pending transactions at info every `every` status polls.
+    pub fn with_pending_log_every(mut self, every: Option<u32>) -> Self {
+        self.pending_log_every = every;
+        self
+    }
+
+    /// Sends the propagation with the given id to the tx sitter, or picks up
+    /// the one already sent, and waits for it to be mined.
+    async fn send_and_monitor(&self, tx_id: String) -> Result<Propagation> {
+        if self.dry_run {
+            info!(
+                network = %self.network,
+                state_bridge_address = %self.state_bridge_address,
+                selector = %PROPAGATE_ROOT_SELECTOR,
+                gas_limit = ?self.gas_limit,
+                "Dry run, not sending root propagation transaction to tx sitter"
+            );
+            return Ok(Propagation::DryRun);
+        }
+
         let ethers_selector = ethers_core::types::Bytes::from_static(
             PROPAGATE_ROOT_SELECTOR.as_ref(),
         );
@@ -128,57 +675,578 @@ impl RelaySigner for TxSitterSigner {
         let send_tx = SendTxRequest {
             to: ethers_address,
             data: Some(ethers_selector),
-            gas_limit: self.gas_limit.map(U256::from).unwrap_or_default(),
-            priority: TransactionPriority::Fast,
-            value: U256::zero(),
-            tx_id: None,
+            gas_limit: self
+                .gas_limit
+                .map(ethers_core::types::U256::from)
+                .unwrap_or_default(),
+            priority: self.priority.into(),
+            value: ethers_core::types::U256::zero(),
+            tx_id: Some(tx_id.clone()),
         };
 
-        let resp = self.tx_sitter.send_tx(&send_tx).await.map_err(|e| {
-            eyre!(
-                "Failed to send root propogation transaction to tx sitter: {}",
-                e
-            )
-        })?;
-
-        info!(
-            tx_id = &resp.tx_id,
-            "Successfully sent root propogation transaction to tx sitter"
-        );
-        let timeout = std::time::Duration::from_secs(120); // TODO: Should be configurable?
-        let backoff = std::time::Duration::from_secs(12);
-        let start = std::time::Instant::now();
-        loop {
-            let tx_response =
-                self.tx_sitter.get_tx(&resp.tx_id).await.map_err(|e| {
-                    eyre!("Failed to get tx status from tx sitter: {}", e)
-                })?;
-
-            match tx_response.status {
-                Some(TxStatus::Mined) | Some(TxStatus::Finalized) => {
+        match self.tx_sitter.send_tx(&send_tx).await {
+            Ok(resp) => {
+                info!(
+                    tx_id = &resp.tx_id,
+                    "Successfully sent root propogation transaction to tx sitter"
+                );
+            }
+            // An earlier run may already have sent the propagation
+            Err(e) => match self.tx_sitter.get_tx(&tx_id).await {
+                Ok(_) => {
                     info!(
-                        tx_id = &resp.tx_id,
-                        "Root propogation transaction mined"
+                        "Root propagation already sent to tx sitter, monitoring it"
                     );
-                    break;
                 }
-                _ => {
-                    info!(
-                        tx_id = &resp.tx_id,
-                        "Root propogation transaction not yet mined"
-                    );
+                Err(_) => {
+                    return Err(eyre!(
+                        "Failed to send root propogation transaction to tx sitter: {}",
+                        e
+                    ));
                 }
+            },
+        }
+
+        let (tx_hash, receipt) = monitor_tx(
+            &self.tx_sitter,
+            &tx_id,
+            self.tx_timeout,
+            self.poll_interval,
+            self.max_poll_interval,
+            self.receipt_provider.as_ref(),
+            self.pending_log_every,
+        )
+        .await
+        .map_err(|e| {
+            // Kept intact so the next attempt is sent under a new id
+            if e.downcast_ref::<TxReverted>().is_some() {
+                e
+            } else {
+                eyre!("Root propogation transaction failed: {e}")
             }
+        })?;
+
+        // The tx sitter doesn't report fees, only verified receipts have them
+        match receipt {
+            Some(receipt) => {
+                record_gas_cost(&self.network, &self.labels, &receipt)
+            }
+            None => debug!(
+                %tx_hash,
+                "No receipt fetched, not recording the propagation gas cost"
+            ),
+        }
+
+        Ok(Propagation::Sent(tx_hash))
+    }
+}
+
+impl From<TxPriority> for TransactionPriority {
+    fn from(priority: TxPriority) -> Self {
+        match priority {
+            TxPriority::Slowest => Self::Slowest,
+            TxPriority::Slow => Self::Slow,
+            TxPriority::Regular => Self::Regular,
+            TxPriority::Fast => Self::Fast,
+            TxPriority::Fastest => Self::Fastest,
+        }
+    }
+}
+
+impl RelaySigner for TxSitterSigner {
+    /// Propogate a new Root to the given network.
+    ///
+    /// This is a long running operation and should probably be awaited in a background task.
+    ///
+    /// The transaction id is derived from the root, see [`propagation_tx_id`].
+    /// The tx sitter refuses a transaction whose id already exists rather
+    /// than sending it again, so when sending fails the existing transaction
+    /// is looked up and monitored instead. A relay restarted after sending
+    /// therefore never propagates the same root twice.
+    ///
+    /// Once a transaction for the root is found to have reverted, later
+    /// attempts use the next id so the root is sent again. After a restart the
+    /// reverted transactions are found again one by one, each failing its
+    /// attempt before the next id is used.
+    async fn propagate_root(&self, root: Field) -> Result<Propagation> {
+        let attempt = self.reverted_attempts(root);
+        let tx_id = propagation_tx_id(root, self.state_bridge_address, attempt);
+        let span = info_span!(
+            "tx_sitter_propagation",
+            network = %self.network,
+            %root,
+            tx_id,
+        );
 
-            if start.elapsed() > timeout {
-                return Err(eyre!("Root propogation transaction timed out"));
+        let result = self.send_and_monitor(tx_id).instrument(span).await;
+        self.record_outcome(root, &result);
+        result
+    }
+}
+
+impl TxSitterSigner {
+    fn reverted_attempts(&self, root: Field) -> u32 {
+        let reverted = self.reverted.lock().expect("reverted lock poisoned");
+        reverted.get(&root).copied().unwrap_or_default()
+    }
+
+    /// Moves on to the next transaction id for the root if its transaction
+    /// reverted, and forgets the root once it has been propagated.
+    fn record_outcome(&self, root: Field, result: &Result<Propagation>) {
+        let mut reverted =
+            self.reverted.lock().expect("reverted lock poisoned");
+        match result {
+            Ok(_) => {
+                reverted.remove(&root);
             }
+            Err(error) if error.downcast_ref::<TxReverted>().is_some() => {
+                let attempts = reverted.entry(root).or_default();
+                *attempts += 1;
+                warn!(
+                    network = %self.network,
+                    %root,
+                    attempts = *attempts,
+                    "Root propagation transaction reverted, the next attempt sends a new one"
+                );
+            }
+            Err(_) => {}
+        }
+    }
+}
+
+/// The tx sitter id of the transaction propagating `root` through the state
+/// bridge, identical across restarts so the tx sitter deduplicates repeated
+/// submissions.
+///
+/// `attempt` counts the reverted transactions for the root, the first
+/// attempt keeps the unsalted id.
+pub fn propagation_tx_id(
+    root: Field,
+    state_bridge_address: Address,
+    attempt: u32,
+) -> String {
+    let mut preimage = root.to_be_bytes::<32>().to_vec();
+    preimage.extend_from_slice(state_bridge_address.as_slice());
+    let id = format!("propagate-root-{}", keccak256(preimage));
+    match attempt {
+        0 => id,
+        attempt => format!("{id}-{attempt}"),
+    }
+}
+
+/// Proposes propagations to a Safe instead of sending them, leaving
+/// execution to its owners.
+pub struct SafeSigner {
+    pub network: String,
+    pub state_bridge_address: Address,
+    pub safe_address: Address,
+    pub service: SafeServiceClient,
+    /// Signs proposals, must be an owner or delegate of the Safe
+    pub proposer: PrivateKeySigner,
+    pub provider: RootProvider<ThrottledTransport>,
+    /// Log the proposal instead of submitting it
+    pub dry_run: bool,
+}
+
+impl RelaySigner for SafeSigner {
+    /// Proposes the propagation to the Safe, returning once the transaction
+    /// service has accepted it.
+    ///
+    /// Nothing is sent on chain, so the propagation is only submitted.
+    async fn propagate_root(&self, _root: Field) -> Result<Propagation> {
+        let nonce = self.service.nonce(self.safe_address).await?;
+        let pending = self
+            .service
+            .pending_transactions(self.safe_address, nonce)
+            .await?;
 
-            std::thread::sleep(backoff);
+        // `propagateRoot()` always propagates the latest root, so a queued
+        // proposal covers every root since
+        if let Some(queued) = pending.iter().find(|tx| {
+            tx.to == self.state_bridge_address
+                && tx.data.as_ref() == Some(&PROPAGATE_ROOT_SELECTOR)
+        }) {
+            info!(
+                network = %self.network,
+                safe = %self.safe_address,
+                safe_tx_hash = %queued.safe_tx_hash,
+                nonce = queued.nonce,
+                "Root propagation already awaiting Safe confirmation"
+            );
+            return Ok(Propagation::Submitted);
         }
 
-        Ok(())
+        // Queue behind any other pending Safe transactions
+        let nonce = pending.first().map_or(nonce, |tx| tx.nonce + 1);
+        let safe_tx = SafeTx::call(
+            self.state_bridge_address,
+            PROPAGATE_ROOT_SELECTOR.clone(),
+            nonce,
+        );
+        let chain_id = self.provider.get_chain_id().await?;
+        let safe_tx_hash = safe_tx.hash(chain_id, self.safe_address);
+
+        if self.dry_run {
+            info!(
+                network = %self.network,
+                safe = %self.safe_address,
+                %safe_tx_hash,
+                nonce,
+                "Dry run, not proposing root propagation to Safe"
+            );
+            return Ok(Propagation::DryRun);
+        }
+
+        let signature = self.proposer.sign_hash(&safe_tx_hash).await?;
+        self.service
+            .propose(
+                self.safe_address,
+                &safe_tx,
+                safe_tx_hash,
+                self.proposer.address(),
+                Bytes::from(signature.as_bytes()),
+            )
+            .await?;
+
+        info!(
+            network = %self.network,
+            safe = %self.safe_address,
+            %safe_tx_hash,
+            nonce,
+            "Proposed root propagation to Safe"
+        );
+
+        Ok(Propagation::Submitted)
     }
 }
 
-signer!(AlloySigner, TxSitterSigner);
+/// Records propagations instead of sending them, responding with
+/// preconfigured results so relay behaviour can be tested without a chain.
+///
+/// Clones share their state, so a clone kept by the test observes the calls
+/// made through the relay.
+///
+/// Only built for the crate's own tests. The crate is a binary, so there are
+/// no downstream crates a `test-util` feature could expose it to.
+#[cfg(test)]
+#[derive(Clone, Default)]
+pub struct MockSigner {
+    state: Arc<std::sync::Mutex<MockState>>,
+}
+
+#[cfg(test)]
+#[derive(Default)]
+struct MockState {
+    calls: Vec<(Field, Instant)>,
+    responses: std::collections::VecDeque<Result<Propagation, String>>,
+    delay: Duration,
+}
+
+#[cfg(test)]
+impl MockSigner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues the result of the next unanswered call. Once the queue is
+    /// exhausted calls succeed without sending a transaction.
+    pub fn respond_with(&self, response: Result<Propagation, String>) {
+        self.state
+            .lock()
+            .expect("mock signer lock poisoned")
+            .responses
+            .push_back(response);
+    }
+
+    /// Makes every later call take `delay` to respond.
+    pub fn delay_by(&self, delay: Duration) {
+        self.state.lock().expect("mock signer lock poisoned").delay = delay;
+    }
+
+    /// The roots `propagate_root` was called with and when, in order.
+    pub fn calls(&self) -> Vec<(Field, Instant)> {
+        self.state
+            .lock()
+            .expect("mock signer lock poisoned")
+            .calls
+            .clone()
+    }
+
+    /// The roots `propagate_root` was called with, in order.
+    pub fn roots(&self) -> Vec<Field> {
+        self.calls().into_iter().map(|(root, _)| root).collect()
+    }
+}
+
+#[cfg(test)]
+impl RelaySigner for MockSigner {
+    async fn propagate_root(&self, root: Field) -> Result<Propagation> {
+        let (response, delay) = {
+            let mut state =
+                self.state.lock().expect("mock signer lock poisoned");
+            state.calls.push((root, Instant::now()));
+            let response = state
+                .responses
+                .pop_front()
+                .unwrap_or(Ok(Propagation::Submitted));
+            (response, state.delay)
+        };
+        tokio::time::sleep(delay).await;
+        response.map_err(|e| eyre!(e))
+    }
+}
+
+signer!(
+    AlloySigner,
+    TxSitterSigner,
+    SafeSigner,
+    #[cfg(test)]
+    MockSigner,
+);
+
+#[cfg(test)]
+mod tests {
+    use alloy::consensus::{Transaction as _, TxEnvelope};
+    use alloy::eips::eip2718::Decodable2718;
+    use axum::routing::post;
+    use axum::{Json, Router};
+    use serde_json::{json, Value};
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
+    use url::Url;
+
+    use super::*;
+
+    /// Serves the JSON-RPC methods a locally signing provider sends a
+    /// transaction through, forwarding every raw transaction sent.
+    async fn stub_l1(raw_txs: mpsc::UnboundedSender<Bytes>) -> Url {
+        let app = Router::new().route(
+            "/",
+            post(move |Json(request): Json<Value>| {
+                let response = l1_response(&request, &raw_txs);
+                async move { Json(response) }
+            }),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        url.parse().unwrap()
+    }
+
+    fn l1_response(
+        request: &Value,
+        raw_txs: &mpsc::UnboundedSender<Bytes>,
+    ) -> Value {
+        let result = match request["method"].as_str() {
+            Some("eth_chainId") => json!("0x1"),
+            Some("eth_getTransactionCount") => json!("0x0"),
+            Some("eth_feeHistory") => json!({
+                "oldestBlock": "0x1",
+                "baseFeePerGas": ["0x3b9aca00", "0x3b9aca00"],
+                "gasUsedRatio": [0.5],
+                "reward": [["0x3b9aca00"]],
+            }),
+            Some("eth_sendRawTransaction") => {
+                let raw: Bytes =
+                    serde_json::from_value(request["params"][0].clone())
+                        .unwrap();
+                let tx_hash = keccak256(&raw);
+                let _ = raw_txs.send(raw);
+                json!(tx_hash)
+            }
+            _ => {
+                return json!({
+                    "jsonrpc": "2.0",
+                    "id": request["id"],
+                    "error": { "code": -32601, "message": "method not found" },
+                })
+            }
+        };
+        json!({
+            "jsonrpc": "2.0",
+            "id": request["id"],
+            "result": result,
+        })
+    }
+
+    /// A local signer sending through `rpc` with the overrides.
+    fn local_signer(rpc: &Url, overrides: TxOverrides) -> AlloySigner {
+        let bridged: BridgedNetworkConfig = toml::from_str(&format!(
+            r#"
+            name = "test"
+            type = "evm"
+            state_bridge_addr = "0x0000000000000000000000000000000000000001"
+            world_id_addr = "0x0000000000000000000000000000000000000002"
+
+            [provider]
+            rpc_endpoint = "{rpc}"
+            "#
+        ))
+        .unwrap();
+        let wallet = EthereumWallet::from(PrivateKeySigner::random());
+        AlloySigner::new(
+            &bridged,
+            bridged.state_bridge_addrs[0],
+            Arc::new(bridged.provider.signer(wallet)),
+            overrides,
+            1,
+            false,
+        )
+    }
+
+    /// Propagates through a local signer with the overrides, returning the
+    /// transaction it sent.
+    async fn sent_transaction(overrides: TxOverrides) -> TxEnvelope {
+        let (raw_txs, mut sent) = mpsc::unbounded_channel();
+        let signer = local_signer(&stub_l1(raw_txs).await, overrides);
+
+        // The receipt never arrives, only the sent transaction matters
+        let propagation =
+            tokio::spawn(
+                async move { signer.propagate_root(Field::from(1)).await },
+            );
+        let raw = tokio::time::timeout(Duration::from_secs(10), sent.recv())
+            .await
+            .expect("no transaction sent")
+            .unwrap();
+        propagation.abort();
+
+        TxEnvelope::decode_2718(&mut raw.as_ref()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn fee_cap_skips_propagation() {
+        let (raw_txs, mut sent) = mpsc::unbounded_channel();
+        let mut signer = local_signer(
+            &stub_l1(raw_txs).await,
+            TxOverrides {
+                gas_limit: Some(100_000),
+                ..Default::default()
+            },
+        );
+        signer.max_tx_fee = Some(U256::from(1));
+
+        let propagation = signer.propagate_root(Field::from(1)).await.unwrap();
+
+        assert_eq!(propagation, Propagation::Skipped);
+        assert!(
+            sent.try_recv().is_err(),
+            "transaction sent over the fee cap"
+        );
+    }
+
+    #[tokio::test]
+    async fn gas_limit_override_is_applied() {
+        let tx = sent_transaction(TxOverrides {
+            gas_limit: Some(123_456),
+            ..Default::default()
+        })
+        .await;
+
+        assert_eq!(tx.gas_limit(), 123_456);
+        assert_eq!(tx.input(), &PROPAGATE_ROOT_SELECTOR);
+    }
+
+    #[tokio::test]
+    async fn gas_limit_override_is_applied_with_fee_overrides() {
+        let tx = sent_transaction(TxOverrides {
+            gas_limit: Some(123_456),
+            max_fee_per_gas: Some(3_000_000_000),
+            max_priority_fee_per_gas: Some(2_000_000_000),
+        })
+        .await;
+
+        assert_eq!(tx.gas_limit(), 123_456);
+        assert_eq!(tx.max_fee_per_gas(), 3_000_000_000);
+        assert_eq!(tx.max_priority_fee_per_gas(), Some(2_000_000_000));
+    }
+
+    fn tx_sitter_signer() -> TxSitterSigner {
+        let bridged: BridgedNetworkConfig = toml::from_str(
+            r#"
+            name = "test"
+            type = "evm"
+            state_bridge_addr = "0x0000000000000000000000000000000000000001"
+            world_id_addr = "0x0000000000000000000000000000000000000002"
+
+            [provider]
+            rpc_endpoint = "http://127.0.0.1:1"
+            "#,
+        )
+        .unwrap();
+        TxSitterSigner::new(
+            &bridged,
+            bridged.state_bridge_addrs[0],
+            TxSitterClient::new("http://127.0.0.1:1/1/api/key", None).unwrap(),
+            None,
+            Duration::from_secs(1),
+            Duration::from_millis(10),
+            false,
+        )
+    }
+
+    #[test]
+    fn tx_ids_are_salted_only_after_a_revert() {
+        let root = Field::from(2);
+        let bridge = Address::with_last_byte(1);
+
+        let first = propagation_tx_id(root, bridge, 0);
+        assert_eq!(first, propagation_tx_id(root, bridge, 0));
+        assert!(!first.ends_with("-0"));
+        assert_eq!(propagation_tx_id(root, bridge, 1), format!("{first}-1"));
+        assert_ne!(first, propagation_tx_id(Field::from(3), bridge, 0));
+    }
+
+    #[test]
+    fn reverted_tx_sitter_transactions_are_sent_again() {
+        let signer = tx_sitter_signer();
+        let root = Field::from(2);
+        let reverted = || -> Result<Propagation> {
+            Err(TxReverted {
+                tx_id: "propagate-root".to_owned(),
+                tx_hash: TxHash::ZERO,
+            }
+            .into())
+        };
+
+        // Timeouts and the like keep monitoring the same transaction
+        signer.record_outcome(root, &Err(eyre!("monitor_tx timed out")));
+        assert_eq!(signer.reverted_attempts(root), 0);
+
+        signer.record_outcome(root, &reverted());
+        signer.record_outcome(root, &reverted());
+        assert_eq!(signer.reverted_attempts(root), 2);
+        assert_eq!(signer.reverted_attempts(Field::from(3)), 0);
+
+        signer.record_outcome(root, &Ok(Propagation::Sent(TxHash::ZERO)));
+        assert_eq!(signer.reverted_attempts(root), 0);
+    }
+
+    #[test]
+    fn fee_cap_applies_to_the_worst_case_fee() {
+        let cap = Some(U256::from(21_000u64 * 100));
+
+        assert_eq!(fee_above_cap(21_000, 100, cap), None);
+        assert_eq!(
+            fee_above_cap(21_000, 101, cap),
+            Some(U256::from(21_000u64 * 101))
+        );
+        assert_eq!(fee_above_cap(21_000, u128::MAX, None), None);
+    }
+
+    #[test]
+    fn bumped_replacements_reach_the_fee_cap() {
+        let cap = Some(U256::from(21_000u64 * 120));
+        let bumped = bump_fee(100, 10);
+
+        assert_eq!(bumped, 110);
+        assert_eq!(fee_above_cap(21_000, bumped, cap), None);
+        assert!(fee_above_cap(21_000, bump_fee(bumped, 10), cap).is_some());
+    }
+
+    #[test]
+    fn bump_raises_fees_by_at_least_one_wei() {
+        assert_eq!(bump_fee(0, 10), 1);
+        assert_eq!(bump_fee(5, 10), 6);
+    }
+}
@@ -0,0 +1,78 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use eyre::eyre::{eyre, Result};
+use tokio::sync::broadcast::Receiver;
+
+use super::{EVMRelay, Relay, Relayer, RootUpdate};
+use crate::config::RestartConfig;
+
+/// Runs the relay, restarting it with exponential backoff whenever it fails.
+///
+/// Returns an error once the relay has failed more than
+/// [`RestartConfig::max_restarts`] times in a row. A relay that ran for at
+/// least the maximum backoff before failing is considered to have recovered,
+/// which resets the count.
+pub async fn supervise(
+    relay: Arc<Relayer>,
+    mut rx: Receiver<RootUpdate>,
+) -> Result<()> {
+    let restart = relay.restart_config();
+    let initial_backoff = Duration::from_millis(restart.initial_backoff_ms);
+    let max_backoff = Duration::from_secs(restart.max_backoff_secs);
+
+    let mut backoff = initial_backoff;
+    let mut restarts = 0;
+
+    loop {
+        let started = Instant::now();
+        // Holding a receiver rather than the sender lets the stream close,
+        // restarts only receive roots sent after them
+        let restart_rx = rx.resubscribe();
+        let Err(error) = relay.subscribe_roots(rx).await else {
+            return Ok(());
+        };
+
+        if started.elapsed() >= max_backoff {
+            restarts = 0;
+            backoff = initial_backoff;
+        }
+
+        log_error(&relay, &error);
+
+        if restarts >= restart.max_restarts {
+            return Err(eyre!(
+                "Relay failed {} consecutive times, giving up: {error}",
+                restarts + 1
+            ));
+        }
+        restarts += 1;
+
+        tracing::warn!(?backoff, restarts, "Restarting relay");
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(max_backoff);
+        rx = restart_rx.resubscribe();
+    }
+}
+
+fn log_error(relay: &Relayer, error: &eyre::Report) {
+    match relay {
+        Relayer::EVMRelay(EVMRelay {
+            name,
+            world_id_address,
+            provider,
+            ..
+        }) => {
+            tracing::error!(
+                %error,
+                %name,
+                %provider,
+                %world_id_address,
+                "Error subscribing to roots"
+            );
+        }
+        Relayer::SvmRelay(_) => {
+            tracing::error!(%error, "Error subscribing to roots");
+        }
+    }
+}
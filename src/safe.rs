@@ -0,0 +1,190 @@
+use alloy::primitives::{Address, Bytes, B256, U256};
+use alloy::sol;
+use alloy::sol_types::{eip712_domain, SolStruct};
+use eyre::eyre::{bail, Result};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+sol! {
+    /// A transaction executed by a Safe once enough owners have confirmed it
+    #[derive(Debug)]
+    struct SafeTx {
+        address to;
+        uint256 value;
+        bytes data;
+        uint8 operation;
+        uint256 safeTxGas;
+        uint256 baseGas;
+        uint256 gasPrice;
+        address gasToken;
+        address refundReceiver;
+        uint256 nonce;
+    }
+}
+
+impl SafeTx {
+    /// A plain call from the Safe, without value or gas refunds.
+    pub fn call(to: Address, data: Bytes, nonce: u64) -> Self {
+        Self {
+            to,
+            value: U256::ZERO,
+            data,
+            operation: 0,
+            safeTxGas: U256::ZERO,
+            baseGas: U256::ZERO,
+            gasPrice: U256::ZERO,
+            gasToken: Address::ZERO,
+            refundReceiver: Address::ZERO,
+            nonce: U256::from(nonce),
+        }
+    }
+
+    /// The hash owners sign, which also identifies the transaction in the
+    /// Safe UI.
+    pub fn hash(&self, chain_id: u64, safe: Address) -> B256 {
+        let domain = eip712_domain! {
+            chain_id: chain_id,
+            verifying_contract: safe,
+        };
+        self.eip712_signing_hash(&domain)
+    }
+}
+
+/// A Safe transaction that has not been executed yet
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingSafeTx {
+    pub safe_tx_hash: B256,
+    pub nonce: u64,
+    pub to: Address,
+    pub data: Option<Bytes>,
+}
+
+#[derive(Deserialize)]
+struct SafeInfo {
+    nonce: u64,
+}
+
+#[derive(Deserialize)]
+struct Page<T> {
+    results: Vec<T>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Proposal<'a> {
+    to: Address,
+    value: String,
+    data: &'a Bytes,
+    operation: u8,
+    safe_tx_gas: String,
+    base_gas: String,
+    gas_price: String,
+    gas_token: Address,
+    refund_receiver: Address,
+    nonce: String,
+    contract_transaction_hash: B256,
+    sender: String,
+    signature: Bytes,
+}
+
+/// Client for the Safe Transaction Service, through which transactions are
+/// proposed to the owners of a Safe.
+pub struct SafeServiceClient {
+    client: reqwest::Client,
+    url: Url,
+}
+
+impl SafeServiceClient {
+    pub fn new(url: Url) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url,
+        }
+    }
+
+    fn safe_url(&self, safe: Address, path: &str) -> String {
+        format!(
+            "{}/api/v1/safes/{}/{path}",
+            self.url.as_str().trim_end_matches('/'),
+            safe.to_checksum(None)
+        )
+    }
+
+    /// The nonce of the next transaction the Safe will execute.
+    pub async fn nonce(&self, safe: Address) -> Result<u64> {
+        let info: SafeInfo = self
+            .client
+            .get(self.safe_url(safe, ""))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(info.nonce)
+    }
+
+    /// The queued transactions with a nonce of at least `nonce`, the highest
+    /// nonce first.
+    pub async fn pending_transactions(
+        &self,
+        safe: Address,
+        nonce: u64,
+    ) -> Result<Vec<PendingSafeTx>> {
+        let page: Page<PendingSafeTx> = self
+            .client
+            .get(self.safe_url(safe, "multisig-transactions/"))
+            .query(&[
+                ("executed", "false"),
+                ("nonce__gte", &nonce.to_string()),
+                ("ordering", "-nonce"),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(page.results)
+    }
+
+    /// Proposes the transaction, signed by `sender`, for confirmation by the
+    /// owners.
+    pub async fn propose(
+        &self,
+        safe: Address,
+        tx: &SafeTx,
+        safe_tx_hash: B256,
+        sender: Address,
+        signature: Bytes,
+    ) -> Result<()> {
+        let proposal = Proposal {
+            to: tx.to,
+            value: tx.value.to_string(),
+            data: &tx.data,
+            operation: tx.operation,
+            safe_tx_gas: tx.safeTxGas.to_string(),
+            base_gas: tx.baseGas.to_string(),
+            gas_price: tx.gasPrice.to_string(),
+            gas_token: tx.gasToken,
+            refund_receiver: tx.refundReceiver,
+            nonce: tx.nonce.to_string(),
+            contract_transaction_hash: safe_tx_hash,
+            sender: sender.to_checksum(None),
+            signature,
+        };
+
+        let response = self
+            .client
+            .post(self.safe_url(safe, "multisig-transactions/"))
+            .json(&proposal)
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            bail!("Safe transaction service rejected the proposal ({status}): {body}");
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,325 @@
+use std::collections::{BTreeMap, HashMap};
+use std::convert::Infallible;
+use std::sync::Arc;
+
+use axum::extract::{Path, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use futures::{Stream, StreamExt};
+use serde_json::{json, Value};
+use tokio::net::TcpListener;
+use tokio_stream::wrappers::BroadcastStream;
+
+use crate::config::ServerConfig;
+use crate::events::RelayEvents;
+use crate::relay::Relayer;
+use crate::status::{NetworkStatus, RelayStatus};
+
+#[derive(Clone)]
+struct AppState {
+    events: RelayEvents,
+    status: RelayStatus,
+    relays: Arc<HashMap<String, Arc<Relayer>>>,
+    admin_token: Option<Arc<str>>,
+    /// The redacted effective configuration
+    config: Arc<str>,
+}
+
+/// Serves the health, status and event stream endpoints until an error
+/// occurs, along with the admin endpoints if an admin token is configured.
+///
+/// `effective_config` is the redacted configuration served on `GET /config`.
+pub async fn serve(
+    config: ServerConfig,
+    effective_config: String,
+    events: RelayEvents,
+    status: RelayStatus,
+    relays: HashMap<String, Arc<Relayer>>,
+) -> eyre::Result<()> {
+    let address = config.address;
+    let app = router(config, effective_config, events, status, relays);
+
+    let listener = TcpListener::bind(address).await?;
+    tracing::info!(%address, "Starting server");
+
+    axum::serve(listener, app).await?;
+
+    Ok(())
+}
+
+fn router(
+    config: ServerConfig,
+    effective_config: String,
+    events: RelayEvents,
+    status: RelayStatus,
+    relays: HashMap<String, Arc<Relayer>>,
+) -> Router {
+    let mut app = Router::new()
+        .route("/health", get(health))
+        .route("/status", get(relay_status))
+        .route("/events", get(events_stream));
+    if config.admin_token.is_some() {
+        app = app
+            .route("/config", get(effective_config_dump))
+            .route("/propagate/:network", post(propagate))
+            .route("/pause/:network", post(pause))
+            .route("/resume/:network", post(resume));
+    }
+    app.with_state(AppState {
+        events,
+        status,
+        relays: Arc::new(relays),
+        admin_token: config.admin_token.map(Arc::from),
+        config: Arc::from(effective_config),
+    })
+}
+
+async fn health() -> StatusCode {
+    StatusCode::OK
+}
+
+/// Reports the propagation state of every relay, keyed by network name.
+async fn relay_status(
+    State(state): State<AppState>,
+) -> Json<BTreeMap<String, NetworkStatus>> {
+    Json(state.status.snapshot())
+}
+
+/// Dumps the effective configuration, after environment and command line
+/// overrides, with secrets redacted.
+///
+/// Requires the configured admin token as a bearer token, as RPC endpoints
+/// may embed API keys.
+async fn effective_config_dump(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<String, (StatusCode, Json<Value>)> {
+    authorize(&state, &headers)?;
+    Ok(state.config.to_string())
+}
+
+/// Propagates the latest canonical root to the network right away.
+///
+/// Requires the configured admin token as a bearer token. Responds with the
+/// propagated root and the transaction hash, which is `null` if no
+/// transaction was sent, or with the error.
+async fn propagate(
+    State(state): State<AppState>,
+    Path(network): Path<String>,
+    headers: HeaderMap,
+) -> (StatusCode, Json<Value>) {
+    let relay = match admin_relay(&state, &network, &headers) {
+        Ok(relay) => relay,
+        Err(response) => return response,
+    };
+
+    tracing::info!(%network, "Manual propagation requested");
+    match relay.propagate_now().await {
+        Ok((root, propagation)) => (
+            StatusCode::OK,
+            Json(json!({
+                "network": network,
+                "root": root,
+                "tx_hash": propagation.tx_hash(),
+            })),
+        ),
+        Err(error) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({ "network": network, "error": error.to_string() })),
+        ),
+    }
+}
+
+/// Stops propagating streamed roots to the network until it is resumed.
+///
+/// Requires the configured admin token as a bearer token.
+async fn pause(
+    State(state): State<AppState>,
+    Path(network): Path<String>,
+    headers: HeaderMap,
+) -> (StatusCode, Json<Value>) {
+    set_paused(state, network, headers, true)
+}
+
+/// Resumes propagating streamed roots to a paused network.
+///
+/// Requires the configured admin token as a bearer token.
+async fn resume(
+    State(state): State<AppState>,
+    Path(network): Path<String>,
+    headers: HeaderMap,
+) -> (StatusCode, Json<Value>) {
+    set_paused(state, network, headers, false)
+}
+
+fn set_paused(
+    state: AppState,
+    network: String,
+    headers: HeaderMap,
+    paused: bool,
+) -> (StatusCode, Json<Value>) {
+    let relay = match admin_relay(&state, &network, &headers) {
+        Ok(relay) => relay,
+        Err(response) => return response,
+    };
+
+    match relay.set_paused(paused) {
+        Ok(()) => (
+            StatusCode::OK,
+            Json(json!({ "network": network, "paused": paused })),
+        ),
+        Err(error) => (
+            StatusCode::BAD_REQUEST,
+            Json(json!({ "network": network, "error": error.to_string() })),
+        ),
+    }
+}
+
+/// Checks the admin bearer token and looks up the network's relay,
+/// returning the error response if either fails.
+fn admin_relay<'a>(
+    state: &'a AppState,
+    network: &str,
+    headers: &HeaderMap,
+) -> Result<&'a Arc<Relayer>, (StatusCode, Json<Value>)> {
+    authorize(state, headers)?;
+
+    state.relays.get(network).ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(json!({ "error": format!("unknown network {network}") })),
+        )
+    })
+}
+
+/// Checks the admin bearer token, returning the error response if it is
+/// missing or wrong.
+fn authorize(
+    state: &AppState,
+    headers: &HeaderMap,
+) -> Result<(), (StatusCode, Json<Value>)> {
+    let authorized = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .zip(state.admin_token.as_deref())
+        .is_some_and(|(token, expected)| token_eq(token, expected));
+    if !authorized {
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            Json(json!({ "error": "invalid or missing bearer token" })),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Compares tokens in time independent of where they first differ.
+fn token_eq(token: &str, expected: &str) -> bool {
+    token.len() == expected.len()
+        && token
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// Streams relay lifecycle events as Server-Sent Events.
+///
+/// Each frame carries a single JSON encoded [`RelayEvent`](crate::events::RelayEvent).
+/// Events missed by a lagging client are skipped.
+async fn events_stream(
+    State(state): State<AppState>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let stream = BroadcastStream::new(state.events.subscribe()).filter_map(
+        |event| async move {
+            let event = event.ok()?;
+            Event::default().json_data(&event).ok().map(Ok)
+        },
+    );
+
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::U256;
+
+    use super::*;
+    use crate::events::RelayEvent;
+
+    /// Serves the router on an ephemeral port, returning its base URL.
+    async fn spawn_server(events: RelayEvents) -> String {
+        let config = ServerConfig {
+            address: "127.0.0.1:0".parse().unwrap(),
+            admin_token: None,
+        };
+        let app = router(
+            config,
+            String::new(),
+            events,
+            RelayStatus::new(),
+            HashMap::new(),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        url
+    }
+
+    #[tokio::test]
+    async fn streams_events_as_json_frames() {
+        let events = RelayEvents::new();
+        let url = spawn_server(events.clone()).await;
+
+        let mut response = reqwest::get(format!("{url}/events")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "text/event-stream"
+        );
+
+        events.emit(RelayEvent::PropagationStarted {
+            network: "optimism".to_owned(),
+            root: U256::from(2),
+        });
+
+        let mut frame = String::new();
+        while !frame.ends_with("\n\n") {
+            let chunk = response.chunk().await.unwrap().unwrap();
+            frame.push_str(std::str::from_utf8(&chunk).unwrap());
+        }
+        let data = frame.strip_prefix("data: ").unwrap().trim_end();
+        let event: Value = serde_json::from_str(data).unwrap();
+        assert_eq!(
+            event,
+            json!({
+                "event": "propagation_started",
+                "network": "optimism",
+                "root": "0x2",
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn admin_endpoints_are_disabled_without_token() {
+        let url = spawn_server(RelayEvents::new()).await;
+
+        let response = reqwest::Client::new()
+            .post(format!("{url}/propagate/optimism"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn compares_tokens() {
+        assert!(token_eq("secret", "secret"));
+        assert!(!token_eq("secret", "secreT"));
+        assert!(!token_eq("secret", "secret-but-longer"));
+        assert!(!token_eq("", "secret"));
+    }
+}
@@ -0,0 +1,175 @@
+use alloy::consensus::Transaction as _;
+use alloy::providers::{Provider, RootProvider};
+use alloy::sol_types::SolCall;
+use alloy::transports::http::{Client, Http};
+use eyre::eyre::{eyre, Result};
+use telemetry_batteries::reexports::metrics;
+
+use crate::abi::IWorldIDIdentityManager::registerIdentitiesCall;
+use crate::block_scanner::TreeChangedLog;
+use crate::config::StartIndexRange;
+use crate::relay::TreeChangeKind;
+
+/// Gates propagation of insertion roots on the `startIndex` of the
+/// originating `registerIdentities` call.
+///
+/// Roots from other kinds of tree changes are always allowed.
+pub struct StartIndexFilter {
+    range: StartIndexRange,
+    provider: RootProvider<Http<Client>>,
+}
+
+impl StartIndexFilter {
+    pub fn new(
+        range: StartIndexRange,
+        provider: RootProvider<Http<Client>>,
+    ) -> Self {
+        Self { range, provider }
+    }
+
+    /// Returns whether the root resulting from this event should be propagated.
+    ///
+    /// Roots whose start index cannot be determined are not propagated.
+    pub async fn allows(&self, log: &TreeChangedLog) -> bool {
+        if TreeChangeKind::from(log.event.kind) != TreeChangeKind::Insertion {
+            return true;
+        }
+
+        let root = log.event.postRoot;
+        match self.start_index(log).await {
+            Ok(start_index) if self.range.contains(start_index) => true,
+            Ok(start_index) => {
+                metrics::counter!("roots_filtered").increment(1);
+                tracing::info!(
+                    %root,
+                    start_index,
+                    min = self.range.min,
+                    max = self.range.max,
+                    "Root is outside of the start index range, not propagating"
+                );
+                false
+            }
+            Err(error) => {
+                metrics::counter!("roots_filtered").increment(1);
+                tracing::warn!(
+                    %root,
+                    %error,
+                    "Failed to determine start index, not propagating"
+                );
+                false
+            }
+        }
+    }
+
+    async fn start_index(&self, log: &TreeChangedLog) -> Result<u32> {
+        let transaction_hash = log
+            .transaction_hash
+            .ok_or_else(|| eyre!("Log is missing its transaction hash"))?;
+
+        let transaction = self
+            .provider
+            .get_transaction_by_hash(transaction_hash)
+            .await?
+            .ok_or_else(|| eyre!("Transaction {transaction_hash} not found"))?;
+
+        decode_start_index(transaction.input())
+    }
+}
+
+/// Decodes the `startIndex` argument of `registerIdentities` calldata.
+fn decode_start_index(input: &[u8]) -> Result<u32> {
+    let call = registerIdentitiesCall::abi_decode(input, true)?;
+    Ok(call.startIndex)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::{B256, U256};
+
+    use super::*;
+    use crate::abi::IWorldIDIdentityManager::TreeChanged;
+
+    /// `registerIdentities` calldata with a start index of 1024 and two
+    /// identity commitments
+    fn register_identities_calldata() -> Vec<u8> {
+        let word = |value: u64| U256::from(value).to_be_bytes::<32>();
+
+        let mut calldata = registerIdentitiesCall::SELECTOR.to_vec();
+        for proof_element in 1..=8 {
+            calldata.extend(word(proof_element));
+        }
+        calldata.extend(word(0xaa)); // preRoot
+        calldata.extend(word(1024)); // startIndex
+        calldata.extend(word(13 * 32)); // identityCommitments offset
+        calldata.extend(word(0xbb)); // postRoot
+        calldata.extend(word(2)); // identityCommitments length
+        calldata.extend(word(0x11));
+        calldata.extend(word(0x22));
+        calldata
+    }
+
+    fn filter(min: u32, max: u32) -> StartIndexFilter {
+        let provider =
+            RootProvider::new_http("http://127.0.0.1:1".parse().unwrap());
+        StartIndexFilter::new(StartIndexRange { min, max }, provider)
+    }
+
+    fn tree_changed(kind: u8) -> TreeChangedLog {
+        TreeChangedLog {
+            event: TreeChanged {
+                preRoot: U256::from(0xaa),
+                kind,
+                postRoot: U256::from(0xbb),
+            },
+            transaction_hash: None,
+        }
+    }
+
+    #[test]
+    fn decodes_the_start_index() {
+        let calldata = register_identities_calldata();
+        assert_eq!(decode_start_index(&calldata).unwrap(), 1024);
+
+        let call = registerIdentitiesCall::abi_decode(&calldata, true).unwrap();
+        assert_eq!(call.postRoot, U256::from(0xbb));
+        assert_eq!(call.identityCommitments.len(), 2);
+    }
+
+    #[test]
+    fn rejects_other_calldata() {
+        let mut calldata = register_identities_calldata();
+        calldata[..4].copy_from_slice(&[0xde, 0xad, 0xbe, 0xef]);
+        assert!(decode_start_index(&calldata).is_err());
+        assert!(decode_start_index(&[]).is_err());
+    }
+
+    #[test]
+    fn range_is_inclusive() {
+        let range = StartIndexRange {
+            min: 1024,
+            max: 2048,
+        };
+        assert!(!range.contains(1023));
+        assert!(range.contains(1024));
+        assert!(range.contains(2048));
+        assert!(!range.contains(2049));
+    }
+
+    #[tokio::test]
+    async fn allows_roots_from_other_tree_changes() {
+        let filter = filter(0, 0);
+        assert!(filter.allows(&tree_changed(1)).await);
+        assert!(filter.allows(&tree_changed(2)).await);
+    }
+
+    #[tokio::test]
+    async fn rejects_insertions_with_unknown_start_index() {
+        let filter = filter(0, u32::MAX);
+        let mut log = tree_changed(0);
+        assert!(!filter.allows(&log).await);
+
+        // The transaction can't be fetched from the unreachable provider
+        log.transaction_hash = Some(B256::repeat_byte(1));
+        assert!(!filter.allows(&log).await);
+    }
+}
@@ -0,0 +1,49 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+
+use semaphore::Field;
+use serde::Serialize;
+
+/// The propagation state of a single relay.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct NetworkStatus {
+    /// The last root successfully propagated to the network
+    pub last_propagated_root: Option<Field>,
+    /// Unix time of the last successful propagation
+    pub last_propagated_at: Option<u64>,
+    /// The error of the last failed propagation, cleared on success
+    pub last_error: Option<String>,
+    /// Whether the circuit breaker is pausing propagation
+    pub circuit_open: bool,
+    /// Whether propagation was paused through `POST /pause/{network}`
+    pub paused: bool,
+    /// The latest canonical root the relay received
+    pub canonical_root: Option<Field>,
+    /// The latest root read from the network
+    pub l2_root: Option<Field>,
+    /// Whether the network's root matched the canonical root when last read
+    pub in_sync: bool,
+}
+
+/// Status of every relay keyed by network name, published by the relays and
+/// served on `GET /status`.
+#[derive(Clone, Debug, Default)]
+pub struct RelayStatus {
+    networks: Arc<RwLock<BTreeMap<String, NetworkStatus>>>,
+}
+
+impl RelayStatus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Applies `f` to the network's status, registering the network if needed.
+    pub fn update(&self, network: &str, f: impl FnOnce(&mut NetworkStatus)) {
+        let mut networks = self.networks.write().expect("status lock poisoned");
+        f(networks.entry(network.to_owned()).or_default());
+    }
+
+    pub fn snapshot(&self) -> BTreeMap<String, NetworkStatus> {
+        self.networks.read().expect("status lock poisoned").clone()
+    }
+}
@@ -1,39 +1,395 @@
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
+use alloy::primitives::TxHash;
+use alloy::providers::{Provider, RootProvider};
+use alloy::rpc::types::TransactionReceipt;
+use alloy::transports::http::{Client, Http};
 use eyre::eyre::{bail, eyre};
-use tx_sitter_client::data::TxStatus;
-use tx_sitter_client::TxSitterClient;
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
+use serde::de::DeserializeOwned;
+use tx_sitter_client::data::{
+    GetTxResponse, SendTxRequest, SendTxResponse, TxStatus,
+};
 
-const MAX_ATTEMPTS: usize = 100;
-const INTERVAL: Duration = Duration::from_secs(5);
+use crate::utils::jitter;
 
-/// Monitor a tx sitter transaction until it is mined
+/// A client of the tx sitter API
 ///
-/// Will make [`MAX_ATTEMPTS`] attempts to get the transaction status from the tx sitter
-/// in intervals of [`INTERVAL`] seconds.
+/// Unlike `tx_sitter_client::TxSitterClient` it can send a bearer token with
+/// every request, for tx sitters behind an auth proxy. Basic auth
+/// credentials in the URL's userinfo are sent as well.
+pub struct TxSitterClient {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl TxSitterClient {
+    pub fn new(url: &str, auth_token: Option<&str>) -> eyre::Result<Self> {
+        let mut headers = HeaderMap::new();
+        if let Some(auth_token) = auth_token {
+            let mut value =
+                HeaderValue::from_str(&format!("Bearer {auth_token}"))
+                    .map_err(|_| {
+                        eyre!("auth_token is not a valid header value")
+                    })?;
+            value.set_sensitive(true);
+            headers.insert(AUTHORIZATION, value);
+        }
+        let client = reqwest::Client::builder()
+            .default_headers(headers)
+            .build()?;
+
+        Ok(Self {
+            client,
+            url: url.trim_end_matches('/').to_owned(),
+        })
+    }
+
+    pub async fn send_tx(
+        &self,
+        req: &SendTxRequest,
+    ) -> eyre::Result<SendTxResponse> {
+        json(self.client.post(format!("{}/tx", self.url)).json(req)).await
+    }
+
+    pub async fn get_tx(&self, tx_id: &str) -> eyre::Result<GetTxResponse> {
+        json(self.client.get(format!("{}/tx/{tx_id}", self.url))).await
+    }
+}
+
+/// Sends the request and decodes its JSON response. Errors leave out the
+/// URL, which embeds the tx sitter's API key.
+async fn json<R: DeserializeOwned>(
+    request: reqwest::RequestBuilder,
+) -> eyre::Result<R> {
+    let response = request
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .map_err(reqwest::Error::without_url)?;
+    Ok(response.json().await.map_err(reqwest::Error::without_url)?)
+}
+
+/// A transaction the tx sitter mined but that reverted, it will never
+/// succeed and must be sent again under a new id
+#[derive(Debug, thiserror::Error)]
+#[error("tx {tx_id} ({tx_hash}) was mined but reverted")]
+pub struct TxReverted {
+    pub tx_id: String,
+    pub tx_hash: TxHash,
+}
+
+/// Monitor a tx sitter transaction until it is mined, returning its hash and
+/// its receipt if one was fetched
+///
+/// Polls the transaction status from the tx sitter until `timeout` has
+/// elapsed. The jittered interval between polls starts at `interval` and
+/// doubles after every poll up to `max_interval`, so fast chains are noticed
+/// quickly without polling slow ones needlessly. If a `receipt_provider` is
+/// given the receipt of the mined transaction is additionally fetched from
+/// the chain and must report success.
+///
+/// The first poll finding the transaction pending is logged at info and a
+/// timeout at warn. Later pending polls are only logged at trace, or at info
+/// every `pending_log_every` polls if set.
 pub async fn monitor_tx(
     client: &TxSitterClient,
     tx_id: &str,
-) -> eyre::Result<()> {
+    timeout: Duration,
+    mut interval: Duration,
+    max_interval: Duration,
+    receipt_provider: Option<&RootProvider<Http<Client>>>,
+    pending_log_every: Option<u32>,
+) -> eyre::Result<(TxHash, Option<TransactionReceipt>)> {
     tracing::info!(tx_id, "monitoring transaction");
-    let mut interval = tokio::time::interval(INTERVAL);
-    // First tick is immediate
-    interval.tick().await;
+    let deadline = Instant::now() + timeout;
+    let mut polls: u32 = 0;
 
-    for _ in 0..MAX_ATTEMPTS {
-        interval.tick().await;
-        let tx = client.get_tx(tx_id).await.map_err(|e| eyre!(e))?;
+    loop {
+        polls = polls.saturating_add(1);
+        let tx = client.get_tx(tx_id).await.map_err(|e| {
+            eyre!("Failed to get tx status from tx sitter: {e}")
+        })?;
 
         match tx.status {
             Some(TxStatus::Mined) | Some(TxStatus::Finalized) => {
-                tracing::info!(tx_id, "tx mined");
-                return Ok(());
+                let tx_hash = tx
+                    .tx_hash
+                    .map(|tx_hash| TxHash::from(tx_hash.0))
+                    .ok_or_else(|| {
+                        eyre!("Mined transaction is missing a hash")
+                    })?;
+                tracing::info!(tx_id, %tx_hash, "tx mined");
+
+                let receipt = match receipt_provider {
+                    Some(provider) => {
+                        Some(check_receipt(provider, tx_id, tx_hash).await?)
+                    }
+                    None => None,
+                };
+
+                return Ok((tx_hash, receipt));
             }
-            _ => {
-                tracing::trace!(tx_id, "tx not yet mined");
+            status => {
+                let sampled =
+                    pending_log_every.is_some_and(|every| polls % every == 0);
+                if polls == 1 || sampled {
+                    tracing::info!(
+                        tx_id,
+                        ?status,
+                        polls,
+                        ?interval,
+                        "tx not yet mined"
+                    );
+                } else {
+                    tracing::trace!(
+                        tx_id,
+                        ?status,
+                        polls,
+                        ?interval,
+                        "tx not yet mined"
+                    );
+                }
             }
         }
+
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            tracing::warn!(
+                tx_id,
+                polls,
+                ?timeout,
+                "tx still not mined, giving up"
+            );
+            bail!("monitor_tx timed out");
+        }
+        tokio::time::sleep(jitter(interval).min(remaining)).await;
+        interval = interval.saturating_mul(2).min(max_interval);
+    }
+}
+
+/// Cross-checks the tx sitter's view of a mined transaction against the chain.
+async fn check_receipt(
+    provider: &RootProvider<Http<Client>>,
+    tx_id: &str,
+    tx_hash: TxHash,
+) -> eyre::Result<TransactionReceipt> {
+    let receipt = provider
+        .get_transaction_receipt(tx_hash)
+        .await?
+        .ok_or_else(|| {
+            eyre!("tx sitter reports {tx_id} mined but no receipt for {tx_hash} was found")
+        })?;
+
+    if !receipt.status() {
+        return Err(TxReverted {
+            tx_id: tx_id.to_owned(),
+            tx_hash,
+        }
+        .into());
     }
 
-    bail!("monitor_tx timed out");
+    Ok(receipt)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use axum::routing::post;
+    use axum::{Json, Router};
+    use serde_json::{json, Value};
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    const TX_HASH: &str =
+        "0x1111111111111111111111111111111111111111111111111111111111111111";
+
+    /// Serves `tx` as the status of every transaction.
+    async fn stub_tx_sitter(tx: Value) -> TxSitterClient {
+        let app = Router::new().fallback(move || {
+            let tx = tx.clone();
+            async move { Json(tx) }
+        });
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url =
+            format!("http://{}/1/api/key", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        TxSitterClient::new(&url, None).unwrap()
+    }
+
+    fn tx(status: &str, tx_hash: Option<&str>) -> Value {
+        let mut tx = json!({
+            "txId": "propagate-root",
+            "to": "0x0000000000000000000000000000000000000001",
+            "value": "0x0",
+            "gasLimit": "0x0",
+            "nonce": 0,
+            "status": status,
+        });
+        if let Some(tx_hash) = tx_hash {
+            tx["txHash"] = json!(tx_hash);
+        }
+        tx
+    }
+
+    #[tokio::test]
+    async fn sends_the_auth_token() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let recorded = seen.clone();
+        let app = Router::new().fallback(move |headers: HeaderMap| {
+            recorded
+                .lock()
+                .unwrap()
+                .push(headers.get(AUTHORIZATION).cloned());
+            async move { Json(tx("pending", None)) }
+        });
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url =
+            format!("http://{}/1/api/key", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        TxSitterClient::new(&url, Some("secret"))
+            .unwrap()
+            .get_tx("propagate-root")
+            .await
+            .unwrap();
+        TxSitterClient::new(&url, None)
+            .unwrap()
+            .get_tx("propagate-root")
+            .await
+            .unwrap();
+
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen[0].as_ref().unwrap(), "Bearer secret");
+        assert!(seen[1].is_none());
+    }
+
+    #[test]
+    fn rejects_invalid_auth_token() {
+        assert!(
+            TxSitterClient::new("http://127.0.0.1:1", Some("a\nb")).is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn pending_tx_times_out() {
+        let client = stub_tx_sitter(tx("pending", None)).await;
+
+        let result = tokio::time::timeout(
+            Duration::from_secs(5),
+            monitor_tx(
+                &client,
+                "propagate-root",
+                Duration::from_millis(200),
+                Duration::from_millis(10),
+                Duration::from_millis(50),
+                None,
+                None,
+            ),
+        )
+        .await
+        .expect("monitor_tx did not respect its timeout");
+
+        let error = result.unwrap_err();
+        assert_eq!(error.to_string(), "monitor_tx timed out");
+    }
+
+    #[tokio::test]
+    async fn mined_tx_returns_its_hash() {
+        let client = stub_tx_sitter(tx("mined", Some(TX_HASH))).await;
+
+        let (tx_hash, receipt) = monitor_tx(
+            &client,
+            "propagate-root",
+            Duration::from_secs(5),
+            Duration::from_millis(10),
+            Duration::from_millis(50),
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(tx_hash, TX_HASH.parse::<TxHash>().unwrap());
+        assert!(receipt.is_none());
+    }
+
+    #[tokio::test]
+    async fn mined_tx_without_hash_fails() {
+        let client = stub_tx_sitter(tx("mined", None)).await;
+
+        let error = monitor_tx(
+            &client,
+            "propagate-root",
+            Duration::from_secs(5),
+            Duration::from_millis(10),
+            Duration::from_millis(50),
+            None,
+            None,
+        )
+        .await
+        .unwrap_err();
+
+        assert_eq!(error.to_string(), "Mined transaction is missing a hash");
+    }
+
+    /// Serves `eth_getTransactionReceipt` with a reverted receipt.
+    async fn stub_reverted_receipts() -> RootProvider<Http<Client>> {
+        let app = Router::new().route(
+            "/",
+            post(|Json(request): Json<Value>| async move {
+                Json(reverted_receipt_response(&request))
+            }),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        RootProvider::new_http(url.parse().unwrap())
+    }
+
+    fn reverted_receipt_response(request: &Value) -> Value {
+        json!({
+            "jsonrpc": "2.0",
+            "id": request["id"],
+            "result": {
+                "type": "0x2",
+                "status": "0x0",
+                "cumulativeGasUsed": "0x5208",
+                "logs": [],
+                "logsBloom": format!("0x{}", "00".repeat(256)),
+                "transactionHash": TX_HASH,
+                "transactionIndex": "0x0",
+                "blockHash": TX_HASH,
+                "blockNumber": "0x1",
+                "gasUsed": "0x5208",
+                "effectiveGasPrice": "0x1",
+                "from": "0x0000000000000000000000000000000000000002",
+                "to": "0x0000000000000000000000000000000000000001",
+                "contractAddress": null,
+            },
+        })
+    }
+
+    #[tokio::test]
+    async fn reverted_tx_is_reported() {
+        let client = stub_tx_sitter(tx("mined", Some(TX_HASH))).await;
+        let receipts = stub_reverted_receipts().await;
+
+        let error = monitor_tx(
+            &client,
+            "propagate-root",
+            Duration::from_secs(5),
+            Duration::from_millis(10),
+            Duration::from_millis(50),
+            Some(&receipts),
+            None,
+        )
+        .await
+        .unwrap_err();
+
+        let reverted = error.downcast_ref::<TxReverted>().unwrap();
+        assert_eq!(reverted.tx_hash, TX_HASH.parse::<TxHash>().unwrap());
+    }
 }
@@ -1,11 +1,58 @@
 use std::future::Future;
 use std::time::Duration;
 
+use rand::Rng;
+use telemetry_batteries::reexports::metrics::{self, Label};
 use tracing::{error, warn};
 
+/// The backoff never grows beyond this when no time limit is given
+pub const MAX_BACKOFF: Duration = Duration::from_secs(300);
+
+/// Fraction of the backoff randomly added or subtracted before each sleep
+pub const JITTER: f64 = 0.2;
+
+/// Runs the task, reporting it on the `task_alive` gauge as 1 while it runs
+/// and 0 once it has exited or been dropped.
+pub async fn track_alive<F: Future>(labels: Vec<Label>, task: F) -> F::Output {
+    let _alive = Alive::new(labels);
+    task.await
+}
+
+struct Alive(Vec<Label>);
+
+impl Alive {
+    fn new(labels: Vec<Label>) -> Self {
+        metrics::gauge!("task_alive", labels.clone()).set(1.0);
+        Self(labels)
+    }
+}
+
+impl Drop for Alive {
+    fn drop(&mut self) {
+        metrics::gauge!("task_alive", self.0.clone()).set(0.0);
+    }
+}
+
+/// Retries `f` with exponential backoff until `limit` is exceeded.
 pub async fn retry<S, F, T, E>(
+    backoff: Duration,
+    limit: Option<Duration>,
+    f: S,
+) -> Result<T, E>
+where
+    F: Future<Output = Result<T, E>> + Send + 'static,
+    S: Fn() -> F + Send + Sync + 'static,
+    E: std::fmt::Debug,
+{
+    retry_with_attempts(backoff, limit, None, f).await
+}
+
+/// Retries `f` with jittered exponential backoff until either `limit` is
+/// exceeded or `max_attempts` calls have failed.
+pub async fn retry_with_attempts<S, F, T, E>(
     mut backoff: Duration,
     limit: Option<Duration>,
+    max_attempts: Option<usize>,
     f: S,
 ) -> Result<T, E>
 where
@@ -13,20 +60,142 @@ where
     S: Fn() -> F + Send + Sync + 'static,
     E: std::fmt::Debug,
 {
+    let mut attempts = 0;
     loop {
         match f().await {
             Ok(res) => return Ok(res),
             Err(e) => {
                 warn!("{e:?}");
+                attempts += 1;
                 if let Some(limit) = limit {
                     if backoff > limit {
                         error!("Retry limit reached: {e:?}");
                         return Err(e);
                     }
                 }
-                tokio::time::sleep(backoff).await;
-                backoff *= 2;
+                if max_attempts.is_some_and(|max| attempts >= max) {
+                    error!(attempts, "Retry attempts exhausted: {e:?}");
+                    return Err(e);
+                }
+                tokio::time::sleep(jitter(backoff)).await;
+                backoff = next_backoff(backoff, limit);
             }
         }
     }
 }
+
+/// Doubles the backoff, saturating just past the limit so it is still
+/// detected as exceeded, or at [`MAX_BACKOFF`] without one.
+fn next_backoff(backoff: Duration, limit: Option<Duration>) -> Duration {
+    let ceiling = limit.map_or(MAX_BACKOFF, |limit| limit.saturating_mul(2));
+    backoff.saturating_mul(2).min(ceiling)
+}
+
+/// Randomly scales the duration by up to [`JITTER`] in either direction,
+/// saturating instead of overflowing.
+pub fn jitter(duration: Duration) -> Duration {
+    let factor = rand::thread_rng().gen_range(1.0 - JITTER..=1.0 + JITTER);
+    Duration::try_from_secs_f64(duration.as_secs_f64() * factor)
+        .unwrap_or(Duration::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use super::*;
+
+    fn failing(
+        calls: &Arc<AtomicUsize>,
+    ) -> impl Fn() -> std::future::Ready<Result<(), ()>> {
+        let calls = calls.clone();
+        move || {
+            calls.fetch_add(1, Ordering::SeqCst);
+            std::future::ready(Err(()))
+        }
+    }
+
+    #[tokio::test]
+    async fn stops_after_max_attempts() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let res = retry_with_attempts(
+            Duration::from_millis(1),
+            None,
+            Some(3),
+            failing(&calls),
+        )
+        .await;
+
+        assert!(res.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn stops_once_backoff_exceeds_limit() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let res = retry_with_attempts(
+            Duration::from_millis(1),
+            Some(Duration::from_millis(4)),
+            None,
+            failing(&calls),
+        )
+        .await;
+
+        // Backs off 1, 2 and 4ms before the backoff exceeds the limit
+        assert!(res.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn returns_the_first_success() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let res = retry_with_attempts(
+            Duration::from_millis(1),
+            None,
+            Some(5),
+            move || {
+                let attempt = counter.fetch_add(1, Ordering::SeqCst);
+                std::future::ready(if attempt < 2 {
+                    Err(())
+                } else {
+                    Ok(attempt)
+                })
+            },
+        )
+        .await;
+
+        assert_eq!(res, Ok(2));
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn backoff_saturates_instead_of_overflowing() {
+        assert_eq!(next_backoff(Duration::MAX, None), MAX_BACKOFF);
+        assert_eq!(
+            next_backoff(Duration::MAX, Some(Duration::MAX)),
+            Duration::MAX
+        );
+        let limit = Duration::from_secs(10);
+        assert_eq!(
+            next_backoff(Duration::from_secs(8), Some(limit)),
+            Duration::from_secs(16)
+        );
+        assert_eq!(
+            next_backoff(Duration::from_secs(16), Some(limit)),
+            Duration::from_secs(20)
+        );
+        assert!(jitter(Duration::MAX) >= Duration::MAX.mul_f64(1.0 - JITTER));
+    }
+
+    #[test]
+    fn jitter_stays_within_bounds() {
+        let backoff = Duration::from_secs(10);
+        for _ in 0..100 {
+            let jittered = jitter(backoff);
+            assert!(jittered >= backoff.mul_f64(1.0 - JITTER));
+            assert!(jittered <= backoff.mul_f64(1.0 + JITTER));
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use alloy::primitives::{Address, U256};
+use alloy::providers::{ProviderBuilder, RootProvider};
+use alloy::transports::http::{Client, Http};
+use futures::future::join_all;
+use telemetry_batteries::reexports::metrics;
+use url::Url;
+
+use crate::abi::IWorldIDIdentityManager::IWorldIDIdentityManagerInstance;
+
+type WorldIdInstance =
+    IWorldIDIdentityManagerInstance<Http<Client>, RootProvider<Http<Client>>>;
+
+/// Cross-checks roots observed on the canonical network against independent
+/// RPC endpoints before they are propagated.
+pub struct RootVerifier {
+    instances: Vec<(Url, WorldIdInstance)>,
+    quorum: usize,
+}
+
+impl RootVerifier {
+    /// Creates a verifier over the given endpoints.
+    ///
+    /// If `quorum` is not set every endpoint must agree.
+    pub fn new(
+        world_id_address: Address,
+        endpoints: &[Url],
+        quorum: Option<usize>,
+    ) -> Self {
+        let instances = endpoints
+            .iter()
+            .map(|endpoint| {
+                let provider = ProviderBuilder::new().on_http(endpoint.clone());
+                (
+                    endpoint.clone(),
+                    IWorldIDIdentityManagerInstance::new(
+                        world_id_address,
+                        provider,
+                    ),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        Self {
+            quorum: quorum.unwrap_or(instances.len()),
+            instances,
+        }
+    }
+
+    /// Returns whether at least `quorum` endpoints know about the root.
+    pub async fn verify(&self, root: U256) -> bool {
+        let results = join_all(self.instances.iter().map(
+            |(endpoint, world_id)| async move {
+                match world_id.queryRoot(root).call().await {
+                    Ok(_) => true,
+                    Err(error) => {
+                        tracing::warn!(
+                            %endpoint,
+                            %root,
+                            %error,
+                            "Verification endpoint does not recognize root"
+                        );
+                        false
+                    }
+                }
+            },
+        ))
+        .await;
+
+        let agreeing = results.into_iter().filter(|agrees| *agrees).count();
+        if agreeing < self.quorum {
+            metrics::counter!("root_verification_failed").increment(1);
+            tracing::error!(
+                %root,
+                agreeing,
+                quorum = self.quorum,
+                "Root failed independent verification, holding propagation"
+            );
+            return false;
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::routing::post;
+    use axum::{Json, Router};
+    use serde_json::{json, Value};
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    /// Serves `queryRoot` calls, either answering with a valid root or
+    /// reverting as if the root was unknown.
+    async fn stub_endpoint(knows_root: bool) -> Url {
+        let app = Router::new().route(
+            "/",
+            post(move |Json(request): Json<Value>| async move {
+                let response = if knows_root {
+                    json!({
+                        "jsonrpc": "2.0",
+                        "id": request["id"],
+                        "result": alloy::hex::encode_prefixed([0u8; 96]),
+                    })
+                } else {
+                    json!({
+                        "jsonrpc": "2.0",
+                        "id": request["id"],
+                        "error": { "code": 3, "message": "execution reverted" },
+                    })
+                };
+                Json(response)
+            }),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        url.parse().unwrap()
+    }
+
+    #[tokio::test]
+    async fn verifies_roots_known_to_every_endpoint() {
+        let endpoints = [stub_endpoint(true).await, stub_endpoint(true).await];
+        let verifier = RootVerifier::new(Address::ZERO, &endpoints, None);
+
+        assert!(verifier.verify(U256::from(1)).await);
+    }
+
+    #[tokio::test]
+    async fn holds_roots_an_endpoint_disagrees_on() {
+        let endpoints = [stub_endpoint(true).await, stub_endpoint(false).await];
+        let verifier = RootVerifier::new(Address::ZERO, &endpoints, None);
+
+        assert!(!verifier.verify(U256::from(1)).await);
+    }
+
+    #[tokio::test]
+    async fn verifies_roots_reaching_the_quorum() {
+        let endpoints = [
+            stub_endpoint(true).await,
+            stub_endpoint(true).await,
+            stub_endpoint(false).await,
+        ];
+
+        let verifier = RootVerifier::new(Address::ZERO, &endpoints, Some(2));
+        assert!(verifier.verify(U256::from(1)).await);
+
+        let verifier = RootVerifier::new(Address::ZERO, &endpoints, Some(3));
+        assert!(!verifier.verify(U256::from(1)).await);
+    }
+}
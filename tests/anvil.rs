@@ -0,0 +1,129 @@
+//! End to end tests against local Anvil chains.
+//!
+//! These require `anvil` on the `PATH` and are ignored by default, run them
+//! with `cargo test -- --ignored`.
+
+mod common;
+
+use std::time::Duration;
+
+use alloy::node_bindings::Anvil;
+use alloy::primitives::U256;
+use alloy::providers::{Provider, ProviderBuilder};
+use eyre::eyre::Result;
+
+#[tokio::test]
+#[ignore = "requires anvil"]
+async fn propagates_root_after_tree_changed() -> Result<()> {
+    let l1 = Anvil::new().spawn();
+    let l2 = Anvil::new().spawn();
+    let stubs = common::Stubs::install(&l1, &l2).await?;
+
+    let _relay = common::spawn_relay(&common::relay_config(&l1, &l2, &stubs))?;
+    // Give the scanner time to reach the chain head
+    tokio::time::sleep(Duration::from_secs(5)).await;
+
+    stubs
+        .emit_tree_changed(&l1, U256::from(1), U256::from(2))
+        .await?;
+
+    common::await_propagation(&l1, stubs.state_bridge, Duration::from_secs(60))
+        .await
+}
+
+#[tokio::test]
+#[ignore = "requires anvil"]
+async fn propagation_lands_on_canonical_chain() -> Result<()> {
+    let l1 = Anvil::new().chain_id(1).spawn();
+    let l2 = Anvil::new().chain_id(10).spawn();
+    let stubs = common::Stubs::install(&l1, &l2).await?;
+
+    let _relay = common::spawn_relay(&common::relay_config(&l1, &l2, &stubs))?;
+    // Give the scanner time to reach the chain head
+    tokio::time::sleep(Duration::from_secs(5)).await;
+
+    stubs
+        .emit_tree_changed(&l1, U256::from(1), U256::from(2))
+        .await?;
+
+    common::await_propagation(&l1, stubs.state_bridge, Duration::from_secs(60))
+        .await?;
+    let on_l2 = common::await_propagation(
+        &l2,
+        stubs.state_bridge,
+        Duration::from_secs(5),
+    )
+    .await;
+    assert!(
+        on_l2.is_err(),
+        "propagateRoot() was sent to the bridged chain"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "requires anvil"]
+async fn reverted_propagation_fails() -> Result<()> {
+    let l1 = Anvil::new().spawn();
+    let l2 = Anvil::new().spawn();
+    let stubs = common::Stubs::install(&l1, &l2).await?;
+    stubs.revert_state_bridge(&l1).await?;
+
+    // A fixed gas limit skips estimation, which would fail on the revert
+    let private_key = common::relay_private_key(&l1);
+    let config = format!(
+        "{}wallet = {{ type = \"private_key\", private_key = \"{private_key}\", gas_limit = 100000 }}\n",
+        common::relay_config(&l1, &l2, &stubs)
+    );
+    let (_relay, mut logs) = common::spawn_relay_with_logs(&config)?;
+
+    // The L2 stub reports a zero root, so the new root is propagated
+    stubs
+        .emit_tree_changed(&l1, U256::from(1), U256::from(2))
+        .await?;
+
+    common::await_log(
+        &mut logs,
+        "Root propagation transaction reverted",
+        Duration::from_secs(60),
+    )
+    .await?;
+    let line = common::await_log(
+        &mut logs,
+        "Failed to propagate root",
+        Duration::from_secs(10),
+    )
+    .await?;
+    assert!(line.contains("reverted"), "unexpected log line: {line}");
+
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "requires anvil"]
+async fn bounded_run_exits_once_range_is_propagated() -> Result<()> {
+    let l1 = Anvil::new().spawn();
+    let l2 = Anvil::new().spawn();
+    let stubs = common::Stubs::install(&l1, &l2).await?;
+
+    stubs
+        .emit_tree_changed(&l1, U256::from(1), U256::from(2))
+        .await?;
+    let end_block = ProviderBuilder::new()
+        .on_http(l1.endpoint_url())
+        .get_block_number()
+        .await?
+        .to_string();
+
+    let status = common::run_relay(
+        &common::relay_config(&l1, &l2, &stubs),
+        &["--start-block", "0", "--end-block", &end_block],
+        Duration::from_secs(60),
+    )
+    .await?;
+
+    assert!(status.success(), "relay exited with {status}");
+    common::await_propagation(&l1, stubs.state_bridge, Duration::from_secs(5))
+        .await
+}
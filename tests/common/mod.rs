@@ -0,0 +1,277 @@
+//! Helpers for running the relay against local Anvil chains.
+
+use std::process::{ExitStatus, Stdio};
+use std::time::{Duration, Instant};
+
+use alloy::consensus::Transaction as _;
+use alloy::hex;
+use alloy::network::{EthereumWallet, TransactionBuilder};
+use alloy::node_bindings::AnvilInstance;
+use alloy::primitives::{address, bytes, keccak256, Address, Bytes, U256};
+use alloy::providers::{Provider, ProviderBuilder};
+use alloy::rpc::types::{BlockTransactionsKind, TransactionRequest};
+use alloy::signers::local::PrivateKeySigner;
+use eyre::eyre::{bail, Result};
+use indoc::formatdoc;
+use tempfile::NamedTempFile;
+use tokio::io::{AsyncBufReadExt, BufReader, Lines};
+use tokio::process::{Child, ChildStdout, Command};
+
+/// keccak256("propagateRoot()")[..4]
+pub static PROPAGATE_ROOT_SELECTOR: Bytes = bytes!("380db829");
+
+/// Emits a `LOG4` whose topics are the four 32 byte words of calldata
+static LOG4_STUB: Bytes = bytes!("60603560403560203560003560006000a400");
+
+/// Returns 32 zero bytes for any call, i.e. a zero `latestRoot()`
+static ZERO_STUB: Bytes = bytes!("60206000f3");
+
+/// Accepts any call
+static NOOP_STUB: Bytes = bytes!("00");
+
+/// Reverts any call
+static REVERT_STUB: Bytes = bytes!("60006000fd");
+
+/// Stub contracts standing in for the World ID deployments
+pub struct Stubs {
+    /// `IWorldIDIdentityManager` on the L1, emits arbitrary logs
+    pub world_id: Address,
+    /// `IStateBridge` on the L1, accepts `propagateRoot()`
+    pub state_bridge: Address,
+    /// `IBridgedWorldID` on the L2, reports a zero `latestRoot()`
+    pub bridged_world_id: Address,
+}
+
+impl Stubs {
+    /// Installs the stubs at fixed addresses on the two chains.
+    pub async fn install(
+        l1: &AnvilInstance,
+        l2: &AnvilInstance,
+    ) -> Result<Self> {
+        let stubs = Self {
+            world_id: address!("0000000000000000000000000000000000001000"),
+            state_bridge: address!("0000000000000000000000000000000000002000"),
+            bridged_world_id: address!(
+                "0000000000000000000000000000000000003000"
+            ),
+        };
+
+        set_code(l1, stubs.world_id, &LOG4_STUB).await?;
+        set_code(l1, stubs.state_bridge, &NOOP_STUB).await?;
+        set_code(l2, stubs.bridged_world_id, &ZERO_STUB).await?;
+
+        Ok(stubs)
+    }
+
+    /// Emits an insertion `TreeChanged` event from the L1 World ID stub.
+    pub async fn emit_tree_changed(
+        &self,
+        l1: &AnvilInstance,
+        pre_root: U256,
+        post_root: U256,
+    ) -> Result<()> {
+        let signer = PrivateKeySigner::from_signing_key(l1.keys()[0].clone());
+        let provider = ProviderBuilder::new()
+            .with_recommended_fillers()
+            .wallet(EthereumWallet::from(signer))
+            .on_http(l1.endpoint_url());
+
+        let topic = keccak256("TreeChanged(uint256,uint8,uint256)");
+        let calldata = [
+            topic.as_slice(),
+            &pre_root.to_be_bytes::<32>(),
+            &U256::ZERO.to_be_bytes::<32>(),
+            &post_root.to_be_bytes::<32>(),
+        ]
+        .concat();
+        let tx = TransactionRequest::default()
+            .with_to(self.world_id)
+            .with_input(calldata);
+        provider.send_transaction(tx).await?.get_receipt().await?;
+
+        Ok(())
+    }
+
+    /// Makes the L1 state bridge stub revert every call.
+    pub async fn revert_state_bridge(&self, l1: &AnvilInstance) -> Result<()> {
+        set_code(l1, self.state_bridge, &REVERT_STUB).await
+    }
+}
+
+async fn set_code(
+    anvil: &AnvilInstance,
+    address: Address,
+    code: &Bytes,
+) -> Result<()> {
+    let provider = ProviderBuilder::new().on_http(anvil.endpoint_url());
+    provider
+        .raw_request::<_, ()>("anvil_setCode".into(), (address, code))
+        .await?;
+    Ok(())
+}
+
+/// Builds a TOML relay config scanning the L1 and relaying to the L2, signing
+/// L1 propagations with the second Anvil account.
+///
+/// The first account emits the test events, so the relay's cached nonce never
+/// goes stale.
+pub fn relay_config(
+    l1: &AnvilInstance,
+    l2: &AnvilInstance,
+    stubs: &Stubs,
+) -> String {
+    let private_key = relay_private_key(l1);
+    formatdoc! {r#"
+        [canonical_network]
+        type = "evm"
+        name = "Anvil L1"
+        world_id_addr = "{world_id}"
+        provider = {{ rpc_endpoint = "{l1}" }}
+        wallet = {{ type = "private_key", private_key = "{private_key}" }}
+
+        [[bridged_networks]]
+        type = "evm"
+        name = "Anvil L2"
+        state_bridge_addr = "{state_bridge}"
+        world_id_addr = "{bridged_world_id}"
+        provider = {{ rpc_endpoint = "{l2}" }}
+        # The World ID stub can't report its latest root
+        reconcile_on_startup = false
+        "#,
+        world_id = stubs.world_id,
+        l1 = l1.endpoint_url(),
+        state_bridge = stubs.state_bridge,
+        bridged_world_id = stubs.bridged_world_id,
+        l2 = l2.endpoint_url(),
+    }
+}
+
+/// The hex encoded key the relay signs L1 propagations with.
+pub fn relay_private_key(l1: &AnvilInstance) -> String {
+    hex::encode(l1.keys()[1].to_bytes())
+}
+
+/// A running relay process, killed when dropped
+pub struct Relay {
+    _child: Child,
+    _config: NamedTempFile,
+}
+
+/// Starts the relay binary with the given TOML config.
+pub fn spawn_relay(config: &str) -> Result<Relay> {
+    let file = tempfile::Builder::new().suffix(".toml").tempfile()?;
+    std::fs::write(file.path(), config)?;
+
+    let child = Command::new(env!("CARGO_BIN_EXE_world-id-relay"))
+        .arg("--config")
+        .arg(file.path())
+        .kill_on_drop(true)
+        .spawn()?;
+
+    Ok(Relay {
+        _child: child,
+        _config: file,
+    })
+}
+
+/// Runs the relay binary with the given TOML config and arguments until it
+/// exits, failing if it is still running after `timeout`.
+pub async fn run_relay(
+    config: &str,
+    args: &[&str],
+    timeout: Duration,
+) -> Result<ExitStatus> {
+    let file = tempfile::Builder::new().suffix(".toml").tempfile()?;
+    std::fs::write(file.path(), config)?;
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_world-id-relay"))
+        .arg("--config")
+        .arg(file.path())
+        .args(args)
+        .kill_on_drop(true)
+        .spawn()?;
+
+    match tokio::time::timeout(timeout, child.wait()).await {
+        Ok(status) => Ok(status?),
+        Err(_) => bail!("Relay still running after {timeout:?}"),
+    }
+}
+
+/// Starts the relay binary with the given TOML config, returning its JSON
+/// log lines.
+///
+/// The lines must be read, the relay blocks once the pipe is full.
+pub fn spawn_relay_with_logs(
+    config: &str,
+) -> Result<(Relay, Lines<BufReader<ChildStdout>>)> {
+    let file = tempfile::Builder::new().suffix(".toml").tempfile()?;
+    std::fs::write(file.path(), config)?;
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_world-id-relay"))
+        .arg("--config")
+        .arg(file.path())
+        .args(["--log-format", "json", "--no-ansi"])
+        .env_remove("RUST_LOG")
+        .stdout(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+    let stdout = child.stdout.take().expect("stdout is piped");
+
+    let relay = Relay {
+        _child: child,
+        _config: file,
+    };
+    Ok((relay, BufReader::new(stdout).lines()))
+}
+
+/// Waits for a log line containing `needle`, returning it.
+pub async fn await_log(
+    lines: &mut Lines<BufReader<ChildStdout>>,
+    needle: &str,
+    timeout: Duration,
+) -> Result<String> {
+    tokio::time::timeout(timeout, async {
+        while let Some(line) = lines.next_line().await? {
+            if line.contains(needle) {
+                return Ok(line);
+            }
+        }
+        bail!("Relay exited without logging {needle:?}")
+    })
+    .await?
+}
+
+/// Waits for a `propagateRoot()` call to the state bridge to be mined on the
+/// given chain.
+pub async fn await_propagation(
+    anvil: &AnvilInstance,
+    state_bridge: Address,
+    timeout: Duration,
+) -> Result<()> {
+    let provider = ProviderBuilder::new().on_http(anvil.endpoint_url());
+    let deadline = Instant::now() + timeout;
+    let mut next_block = 0;
+
+    while Instant::now() < deadline {
+        let latest = provider.get_block_number().await?;
+        for number in next_block..=latest {
+            let Some(block) = provider
+                .get_block_by_number(number.into(), BlockTransactionsKind::Full)
+                .await?
+            else {
+                continue;
+            };
+            let propagated = block.transactions.txns().any(|tx| {
+                tx.to() == Some(state_bridge)
+                    && tx.input().starts_with(&PROPAGATE_ROOT_SELECTOR)
+            });
+            if propagated {
+                return Ok(());
+            }
+        }
+        next_block = latest + 1;
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+
+    bail!("propagateRoot() was not called within {timeout:?}")
+}
@@ -0,0 +1,62 @@
+//! Checks that secrets stay out of the startup log.
+
+use std::process::Stdio;
+use std::time::Duration;
+
+use eyre::eyre::{bail, Result};
+use indoc::formatdoc;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::Command;
+
+const MNEMONIC: &str =
+    "test test test test test test test test test test test junk";
+
+#[tokio::test]
+async fn startup_log_redacts_mnemonic() -> Result<()> {
+    // Nothing listens on the endpoint, the relay fails after logging its
+    // configuration
+    let config = formatdoc! {r#"
+        [canonical_network]
+        type = "evm"
+        name = "Canonical"
+        world_id_addr = "0xb2ead588f14e69266d1b87936b75325181377076"
+        provider = {{ rpc_endpoint = "http://127.0.0.1:1" }}
+        wallet = {{ type = "mnemonic", mnemonic = "{MNEMONIC}" }}
+
+        [[bridged_networks]]
+        type = "evm"
+        name = "Bridged"
+        state_bridge_addr = "0x2F418Aa7D500B525EE8B80BB5F643A877ef82e09"
+        world_id_addr = "0xE177F37AF0A862A02edFEa4F59C02668E9d0aAA4"
+        provider = {{ rpc_endpoint = "http://127.0.0.1:1" }}
+        "#,
+    };
+    let file = tempfile::Builder::new().suffix(".toml").tempfile()?;
+    std::fs::write(file.path(), config)?;
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_world-id-relay"))
+        .arg("--config")
+        .arg(file.path())
+        .args(["--log-format", "json", "--no-ansi"])
+        .env_remove("RUST_LOG")
+        .stdout(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+    let stdout = child.stdout.take().expect("stdout is piped");
+    let mut lines = BufReader::new(stdout).lines();
+
+    let line = tokio::time::timeout(Duration::from_secs(30), async {
+        while let Some(line) = lines.next_line().await? {
+            if line.contains("Starting world-id-relay") {
+                return Ok(line);
+            }
+        }
+        bail!("Relay exited without logging its configuration")
+    })
+    .await??;
+
+    assert!(line.contains("<redacted>"), "unexpected log line: {line}");
+    assert!(!line.contains(MNEMONIC), "mnemonic leaked: {line}");
+
+    Ok(())
+}